@@ -59,11 +59,16 @@ elif [ "$PLATFORM" == "linux" ]; then
     
     # Run Tauri build in container (no volume over target so outputs reach host)
     # Use MSYS_NO_PATHCONV to prevent Git Bash path conversion on Windows
+    # The native messaging host is built first and shipped next to the app as
+    # a sidecar, which Tauri expects under binaries/ with the target triple
     MSYS_NO_PATHCONV=1 docker run --rm \
         -v "$(pwd):/app" \
         -w /app/src-tauri \
         focuslock-linux-builder \
-        cargo tauri build
+        sh -c 'cargo build --release --bin focuslock-native-host \
+            && mkdir -p binaries \
+            && cp target/release/focuslock-native-host "binaries/focuslock-native-host-$(rustc -vV | sed -n "s/^host: //p")" \
+            && cargo tauri build --config tauri.native-host.conf.json'
     
     # Copy outputs
     mkdir -p releases/linux
@@ -74,7 +74,8 @@ export default function RulesTable({
       'package': 'Package',
       'lnk': 'Shortcut',
       'path': 'Path',
-      'regex': 'Pattern'
+      'regex': 'Pattern',
+      'domain': 'Website'
     };
     return labels[kind];
   };
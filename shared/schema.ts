@@ -15,7 +15,7 @@ export const favorites = pgTable("favorites", {
 export const blockRules = pgTable("block_rules", {
   id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
   appId: text("app_id").notNull(),
-  matchKind: text("match_kind").notNull(), // 'exe'|'package'|'lnk'|'path'|'regex'
+  matchKind: text("match_kind").notNull(), // 'exe'|'package'|'lnk'|'path'|'regex'|'domain' (regex appId may be prefixed with name:, path: or cmdline:; domain appId may start with *.)
   mode: text("mode").notNull(), // 'hard'|'soft'
 });
 
@@ -23,7 +23,7 @@ export const sessions = pgTable("sessions", {
   id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
   startUtc: integer("start_utc").notNull(),
   endUtc: integer("end_utc").notNull(),
-  status: text("status").notNull(), // 'scheduled'|'running'|'completed'|'canceled'
+  status: text("status").notNull(), // 'scheduled'|'running'|'paused'|'completed'|'canceled'
   durationSecs: integer("duration_secs").notNull(),
   remainingSecs: integer("remaining_secs"),
 });
@@ -34,9 +34,9 @@ export const settings = pgTable("settings", {
 });
 
 // Enum schemas for validation
-export const matchKindSchema = z.enum(['exe', 'package', 'lnk', 'path', 'regex']);
-export const blockModeSchema = z.enum(['hard', 'soft']);
-export const sessionStatusSchema = z.enum(['scheduled', 'running', 'paused', 'completed', 'canceled']);
+export const matchKindSchema = z.enum(['exe', 'package', 'lnk', 'path', 'regex', 'domain']);
+export const blockModeSchema = z.enum(['hard', 'soft', 'freeze']);
+export const sessionStatusSchema = z.enum(['scheduled', 'running', 'paused', 'completed', 'canceled', 'skipped']);
 
 // Insert schemas
 export const insertFavoriteSchema = createInsertSchema(favorites).omit({ id: true });
@@ -65,9 +65,9 @@ export type Setting = typeof settings.$inferSelect;
 export type InsertSetting = z.infer<typeof insertSettingSchema>;
 
 // Additional types for the app
-export type MatchKind = 'exe' | 'package' | 'lnk' | 'path' | 'regex';
-export type BlockMode = 'hard' | 'soft';
-export type SessionStatus = 'scheduled' | 'running' | 'paused' | 'completed' | 'canceled';
+export type MatchKind = 'exe' | 'package' | 'lnk' | 'path' | 'regex' | 'domain';
+export type BlockMode = 'hard' | 'soft' | 'freeze';
+export type SessionStatus = 'scheduled' | 'running' | 'paused' | 'completed' | 'canceled' | 'skipped';
 
 export interface AppSummary {
   appId: string;
@@ -0,0 +1,343 @@
+// FocusLock native messaging host
+//
+// Started by the browser on behalf of the FocusLock extension. It reads the
+// current session and its domain rules straight from focuslock.db, so it
+// works whether or not the app window is open, and records pages the
+// extension blocked as block events (action `url_blocked`, the site as the
+// process name and the URL without its query or fragment as the path).
+//
+// `--install [chrome extension id ...]` and `--uninstall` manage the browser
+// manifests on Linux. Only protocol messages go to stdout; logs go to stderr.
+
+#[allow(dead_code)]
+#[path = "../../domains/rule.rs"]
+mod domain_rule;
+#[cfg(target_os = "linux")]
+mod manifests;
+// The app's schema, so tests run against the tables the host really reads
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../../migrations.rs"]
+mod migrations;
+mod protocol;
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Result as SqliteResult};
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+use domain_rule::parse_domain_rule;
+use protocol::{DomainRuleState, Request, Response, SessionState};
+
+// Tauri's identifier, which names the app data directory
+const APP_IDENTIFIER: &str = "com.focuslock.desktop";
+const URL_BLOCKED_ACTION: &str = "url_blocked";
+// The app may be writing at the same moment
+const BUSY_TIMEOUT: Duration = Duration::from_secs(2);
+// Profiles (v7) are the newest part of the schema read here
+const MIN_SCHEMA_VERSION: i64 = 7;
+
+fn now_utc() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// Where the app keeps focuslock.db; FOCUSLOCK_DB overrides it
+fn db_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("FOCUSLOCK_DB") {
+        return Some(path.into());
+    }
+
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+    let data_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|h| h.join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home().map(|h| h.join(".local/share")))
+    };
+    data_dir.map(|dir| dir.join(APP_IDENTIFIER).join("focuslock.db"))
+}
+
+// Never creates or migrates the database; that is the app's job
+fn open_db() -> Result<Connection, String> {
+    let path = db_path().ok_or("Couldn't locate FocusLock's data directory")?;
+    let conn = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_WRITE)
+        .map_err(|e| format!("Couldn't open {} (has FocusLock been started?): {}", path.display(), e))?;
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(|e| e.to_string())?;
+
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if version < MIN_SCHEMA_VERSION {
+        return Err("FocusLock's database is out of date; start the app once to update it".to_string());
+    }
+    Ok(conn)
+}
+
+// The running or paused session, with its profile. Enforcing is false while
+// paused or on a pomodoro break (an open interval that isn't work).
+fn current_session(conn: &Connection, now: i64) -> SqliteResult<Option<(SessionState, Option<String>)>> {
+    conn.query_row(
+        "SELECT s.id, s.status, s.end_utc, s.profile_id,
+                s.status = 'running' AND NOT EXISTS (
+                    SELECT 1 FROM session_intervals i
+                     WHERE i.session_id = s.id AND i.ended_utc IS NULL AND i.kind != 'work')
+           FROM sessions s
+          WHERE s.status = 'paused' OR (s.status = 'running' AND s.end_utc > ?1)
+          ORDER BY s.start_utc DESC
+          LIMIT 1",
+        [now],
+        |row| {
+            let session = SessionState {
+                id: row.get(0)?,
+                status: row.get(1)?,
+                end_utc: row.get(2)?,
+                enforcing: row.get(4)?,
+            };
+            Ok((session, row.get(3)?))
+        },
+    )
+    .optional()
+}
+
+// Domain rules of the session's rule set; invalid ones are skipped like the
+// app does
+fn domain_rules(conn: &Connection, profile_id: Option<&str>) -> SqliteResult<Vec<DomainRuleState>> {
+    let mut stmt = conn.prepare("SELECT id, app_id, mode FROM block_rules WHERE match_kind = 'domain' AND profile_id IS ?1")?;
+    let rows = stmt
+        .query_map([profile_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(rule_id, app_id, mode)| {
+            let rule = parse_domain_rule(&app_id).ok()?;
+            Some(DomainRuleState {
+                rule_id,
+                domain: rule.domain,
+                wildcard: rule.wildcard,
+                mode,
+            })
+        })
+        .collect())
+}
+
+fn get_state(conn: &Connection, now: i64) -> SqliteResult<Response> {
+    let Some((session, profile_id)) = current_session(conn, now)? else {
+        return Ok(Response::State {
+            session: None,
+            rules: Vec::new(),
+        });
+    };
+    let rules = domain_rules(conn, profile_id.as_deref())?;
+    Ok(Response::State {
+        session: Some(session),
+        rules,
+    })
+}
+
+fn record_blocked(conn: &Connection, url: &str, rule_id: Option<&str>, now: i64) -> SqliteResult<Response> {
+    let session = current_session(conn, now)?;
+    // The extension's rule id is only trusted if it names a domain rule the
+    // current session actually enforces
+    let rule_id = match (&session, rule_id) {
+        (Some((_, profile_id)), Some(rule_id)) => domain_rules(conn, profile_id.as_deref())?
+            .into_iter()
+            .any(|rule| rule.rule_id == rule_id)
+            .then_some(rule_id),
+        _ => None,
+    };
+    let session_id = session.map(|(session, _)| session.id);
+    // Grouped by site in the stats, like process names
+    let site = parse_domain_rule(url).map(|rule| rule.domain).unwrap_or_else(|_| url.to_string());
+    // Queries and fragments can carry tokens or search terms; keep the page
+    let page = url.split(['?', '#']).next().unwrap_or(url);
+
+    conn.execute(
+        "INSERT INTO block_events (id, timestamp_utc, session_id, rule_id, process_name, exe_path, pid, action)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, NULL, ?7)",
+        (Uuid::new_v4().to_string(), now, session_id, rule_id, site, page, URL_BLOCKED_ACTION),
+    )?;
+    Ok(Response::Recorded)
+}
+
+fn handle(conn: &Result<Connection, String>, body: &[u8]) -> Response {
+    let request = match serde_json::from_slice::<Request>(body) {
+        Ok(request) => request,
+        Err(e) => return Response::Error { message: format!("Bad request: {}", e) },
+    };
+
+    let with_db = |f: &dyn Fn(&Connection) -> SqliteResult<Response>| match conn {
+        Ok(conn) => f(conn).unwrap_or_else(|e| Response::Error { message: e.to_string() }),
+        Err(message) => Response::Error { message: message.clone() },
+    };
+
+    let now = now_utc();
+    match request {
+        Request::Ping => Response::Pong {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        },
+        Request::GetState => with_db(&|conn| get_state(conn, now)),
+        Request::ReportBlocked { url, rule_id } => with_db(&|conn| record_blocked(conn, &url, rule_id.as_deref(), now)),
+    }
+}
+
+fn serve() -> io::Result<()> {
+    // Opened once; a failure is reported on every message rather than
+    // exiting, so the extension can show why
+    let conn = open_db();
+    if let Err(e) = &conn {
+        eprintln!("[NativeHost] {}", e);
+    }
+
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    while let Some(body) = protocol::read_message(&mut input)? {
+        protocol::write_message(&mut output, &handle(&conn, &body))?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn manage_manifests(install: bool, chrome_ids: &[String]) -> io::Result<()> {
+    let home = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))?;
+
+    let paths = if install {
+        for id in chrome_ids {
+            manifests::validate_chrome_id(id).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        }
+        let host_path = std::env::current_exe()?.canonicalize()?;
+        manifests::install(&home, &host_path, chrome_ids)?
+    } else {
+        manifests::uninstall(&home)?
+    };
+
+    for path in paths {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn manage_manifests(_install: bool, _chrome_ids: &[String]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Installing browser manifests is only supported on Linux",
+    ))
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("--install") => manage_manifests(true, &args[1..]),
+        Some("--uninstall") => manage_manifests(false, &[]),
+        // Browsers pass the extension origin or manifest path; not needed
+        _ => serve(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("[NativeHost] {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn exchange(conn: &Result<Connection, String>, request: Value) -> Value {
+        // Through the real framing both ways
+        let mut framed = Vec::new();
+        let body = serde_json::to_vec(&request).unwrap();
+        framed.extend_from_slice(&(body.len() as u32).to_ne_bytes());
+        framed.extend_from_slice(&body);
+        let body = protocol::read_message(&mut framed.as_slice()).unwrap().unwrap();
+
+        let mut out = Vec::new();
+        protocol::write_message(&mut out, &handle(conn, &body)).unwrap();
+        assert_eq!(u32::from_ne_bytes(out[..4].try_into().unwrap()) as usize, out.len() - 4);
+        serde_json::from_slice(&out[4..]).unwrap()
+    }
+
+    #[test]
+    fn serves_session_rules_and_records_blocked_urls() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations::migrate(&mut conn, None).unwrap();
+        assert!(migrations::latest_version() as i64 >= MIN_SCHEMA_VERSION);
+        conn.execute_batch(
+            "INSERT INTO block_profiles (id, name, created_utc) VALUES ('p1', 'Deep work', 0);
+             INSERT INTO block_rules (id, app_id, match_kind, mode, profile_id) VALUES ('r1', '*.reddit.com', 'domain', 'hard', 'p1');
+             INSERT INTO block_rules (id, app_id, match_kind, mode, profile_id) VALUES ('r2', 'steam', 'exe', 'hard', 'p1');
+             INSERT INTO block_rules (id, app_id, match_kind, mode, profile_id) VALUES ('r3', 'news.com', 'domain', 'soft', NULL);",
+        )
+        .unwrap();
+        let conn = Ok(conn);
+
+        assert_eq!(exchange(&conn, json!({ "type": "getState" })), json!({ "type": "state", "session": null, "rules": [] }));
+
+        conn.as_ref()
+            .unwrap()
+            .execute(
+                "INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs, profile_id)
+                 VALUES ('s1', 0, ?1, 'running', 600, 'p1')",
+                [now_utc() + 600],
+            )
+            .unwrap();
+        let state = exchange(&conn, json!({ "type": "getState" }));
+        assert_eq!(state["session"]["id"], "s1");
+        assert_eq!(state["session"]["enforcing"], true);
+        assert_eq!(
+            state["rules"],
+            json!([{ "ruleId": "r1", "domain": "reddit.com", "wildcard": true, "mode": "hard" }])
+        );
+
+        let reply = exchange(
+            &conn,
+            json!({ "type": "reportBlocked", "url": "https://old.reddit.com/r/rust?q=secret#top", "ruleId": "r1" }),
+        );
+        assert_eq!(reply, json!({ "type": "recorded" }));
+        let (session_id, site, page): (String, String, String) = conn
+            .as_ref()
+            .unwrap()
+            .query_row(
+                "SELECT session_id, process_name, exe_path FROM block_events WHERE action = 'url_blocked'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (session_id.as_str(), site.as_str(), page.as_str()),
+            ("s1", "old.reddit.com", "https://old.reddit.com/r/rust")
+        );
+
+        // Ids of app rules, other profiles' rules or unknown rules aren't kept
+        for rule_id in ["r1", "r2", "r3", "made-up"] {
+            exchange(&conn, json!({ "type": "reportBlocked", "url": "https://reddit.com/", "ruleId": rule_id }));
+        }
+        let kept: Vec<Option<String>> = conn
+            .as_ref()
+            .unwrap()
+            .prepare("SELECT rule_id FROM block_events WHERE exe_path = 'https://reddit.com/' ORDER BY rowid")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<SqliteResult<_>>()
+            .unwrap();
+        assert_eq!(kept, vec![Some("r1".to_string()), None, None, None]);
+
+        assert_eq!(exchange(&conn, json!({ "type": "nope" }))["type"], "error");
+        let missing = Err("no database".to_string());
+        assert_eq!(exchange(&missing, json!({ "type": "ping" }))["type"], "pong");
+        assert_eq!(exchange(&missing, json!({ "type": "getState" }))["message"], "no database");
+    }
+}
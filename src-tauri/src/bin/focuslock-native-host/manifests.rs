@@ -0,0 +1,138 @@
+// Host manifests in the per-user native messaging directories (Linux)
+//
+// Chromium-based browsers only start a host for the extension origins its
+// manifest lists, so their manifests are written only when extension ids are
+// given; Firefox's lists the add-on id instead. Browsers without a profile
+// directory (never run, or not installed) are skipped.
+
+use serde_json::{json, Value};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const HOST_NAME: &str = "com.focuslock.native_host";
+const FIREFOX_EXTENSION_ID: &str = "browser-bridge@focuslock.app";
+const DESCRIPTION: &str = "FocusLock browser bridge";
+
+// Profile root and manifest directory, relative to $HOME
+const CHROMIUM_DIRS: &[(&str, &str)] = &[
+    (".config/google-chrome", "NativeMessagingHosts"),
+    (".config/chromium", "NativeMessagingHosts"),
+    (".config/BraveSoftware/Brave-Browser", "NativeMessagingHosts"),
+    (".config/microsoft-edge", "NativeMessagingHosts"),
+    (".config/vivaldi", "NativeMessagingHosts"),
+];
+const FIREFOX_DIR: (&str, &str) = (".mozilla", "native-messaging-hosts");
+
+// Chrome extension ids are 32 letters from a to p
+pub fn validate_chrome_id(id: &str) -> Result<(), String> {
+    if id.len() == 32 && id.chars().all(|c| ('a'..='p').contains(&c)) {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a Chrome extension id", id))
+    }
+}
+
+fn chromium_manifest(host_path: &Path, extension_ids: &[String]) -> Value {
+    let origins: Vec<String> = extension_ids.iter().map(|id| format!("chrome-extension://{}/", id)).collect();
+    json!({
+        "name": HOST_NAME,
+        "description": DESCRIPTION,
+        "path": host_path,
+        "type": "stdio",
+        "allowed_origins": origins,
+    })
+}
+
+fn firefox_manifest(host_path: &Path) -> Value {
+    json!({
+        "name": HOST_NAME,
+        "description": DESCRIPTION,
+        "path": host_path,
+        "type": "stdio",
+        "allowed_extensions": [FIREFOX_EXTENSION_ID],
+    })
+}
+
+// Manifest locations for every browser with a profile under `home`, each with
+// the manifest it should hold
+fn targets(home: &Path, host_path: &Path, chrome_ids: &[String]) -> Vec<(PathBuf, Value)> {
+    let file_name = format!("{}.json", HOST_NAME);
+    let mut targets = Vec::new();
+
+    if !chrome_ids.is_empty() {
+        for (root, dir) in CHROMIUM_DIRS {
+            if home.join(root).is_dir() {
+                targets.push((home.join(root).join(dir).join(&file_name), chromium_manifest(host_path, chrome_ids)));
+            }
+        }
+    }
+
+    let (root, dir) = FIREFOX_DIR;
+    if home.join(root).is_dir() {
+        targets.push((home.join(root).join(dir).join(&file_name), firefox_manifest(host_path)));
+    }
+    targets
+}
+
+// Writes the manifests and returns their paths
+pub fn install(home: &Path, host_path: &Path, chrome_ids: &[String]) -> io::Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for (path, manifest) in targets(home, host_path, chrome_ids) {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(&manifest)?)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+// Removes every manifest FocusLock may have written and returns their paths
+pub fn uninstall(home: &Path) -> io::Result<Vec<PathBuf>> {
+    let file_name = format!("{}.json", HOST_NAME);
+    let mut removed = Vec::new();
+
+    for (root, dir) in CHROMIUM_DIRS.iter().chain([&FIREFOX_DIR]) {
+        let path = home.join(root).join(dir).join(&file_name);
+        match fs::remove_file(&path) {
+            Ok(()) => removed.push(path),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifests_go_to_browsers_with_a_profile() {
+        let home = std::env::temp_dir().join(format!("focuslock-manifests-{}", std::process::id()));
+        fs::create_dir_all(home.join(".config/chromium")).unwrap();
+        fs::create_dir_all(home.join(".mozilla")).unwrap();
+        let host = Path::new("/opt/focuslock/focuslock-native-host");
+        let chrome_id = "abcdefghijklmnopabcdefghijklmnop".to_string();
+
+        assert!(validate_chrome_id(&chrome_id).is_ok());
+        assert!(validate_chrome_id("not-an-id").is_err());
+
+        // Without an extension id only Firefox can be set up
+        let written = install(&home, host, &[]).unwrap();
+        assert_eq!(written, vec![home.join(".mozilla/native-messaging-hosts/com.focuslock.native_host.json")]);
+
+        let written = install(&home, host, &[chrome_id]).unwrap();
+        assert_eq!(written.len(), 2);
+        let chromium: Value = serde_json::from_str(&fs::read_to_string(&written[0]).unwrap()).unwrap();
+        assert_eq!(chromium["path"], "/opt/focuslock/focuslock-native-host");
+        assert_eq!(chromium["allowed_origins"][0], "chrome-extension://abcdefghijklmnopabcdefghijklmnop/");
+        assert!(!home.join(".config/google-chrome").exists());
+
+        assert_eq!(uninstall(&home).unwrap().len(), 2);
+        assert!(uninstall(&home).unwrap().is_empty());
+
+        fs::remove_dir_all(&home).unwrap();
+    }
+}
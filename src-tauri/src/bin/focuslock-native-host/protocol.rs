@@ -0,0 +1,78 @@
+// Native messaging framing and messages
+//
+// Chrome and Firefox talk to the host over stdio: each message is UTF-8 JSON
+// preceded by its length as a 32-bit integer in native byte order. Messages
+// are tagged by `type`.
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+
+// Browsers cap host replies at 1 MB; nothing the extension sends comes close
+pub const MAX_MESSAGE_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum Request {
+    Ping,
+    GetState,
+    // The extension blocked a page
+    ReportBlocked { url: String, rule_id: Option<String> },
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionState {
+    pub id: String,
+    pub status: String,
+    pub end_utc: i64,
+    // False on a pomodoro break
+    pub enforcing: bool,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainRuleState {
+    pub rule_id: String,
+    pub domain: String,
+    pub wildcard: bool,
+    // hard/freeze block the page, soft only warns
+    pub mode: String,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum Response {
+    Pong { version: String },
+    State { session: Option<SessionState>, rules: Vec<DomainRuleState> },
+    Recorded,
+    Error { message: String },
+}
+
+// None at a clean end of input, which is how the browser says goodbye
+pub fn read_message(input: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match input.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let len = u32::from_ne_bytes(len) as usize;
+    if len > MAX_MESSAGE_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Message of {} bytes is over the {} byte limit", len, MAX_MESSAGE_BYTES),
+        ));
+    }
+
+    let mut body = vec![0u8; len];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+pub fn write_message(output: &mut impl Write, response: &Response) -> io::Result<()> {
+    let body = serde_json::to_vec(response)?;
+    output.write_all(&(body.len() as u32).to_ne_bytes())?;
+    output.write_all(&body)?;
+    output.flush()
+}
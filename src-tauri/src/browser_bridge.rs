@@ -0,0 +1,50 @@
+// Browser extension bridge setup
+//
+// The native messaging host is its own binary shipped next to the app (see
+// src/bin/focuslock-native-host). These commands run its `--install` and
+// `--uninstall` modes so the browser manifests point at the installed copy.
+
+use crate::error::{CommandResult, FocusLockError};
+
+#[cfg(target_os = "linux")]
+fn run_host(args: &[String]) -> CommandResult<Vec<String>> {
+    let host = std::env::current_exe()?.with_file_name("focuslock-native-host");
+    if !host.is_file() {
+        return Err(FocusLockError::Platform(format!(
+            "The native messaging host is missing from {}",
+            host.display()
+        )));
+    }
+
+    let output = std::process::Command::new(&host).args(args).output()?;
+    if !output.status.success() {
+        return Err(FocusLockError::Platform(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    // One manifest path per line
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(String::from).collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn run_host(_args: &[String]) -> CommandResult<Vec<String>> {
+    Err(FocusLockError::Unsupported(
+        "Installing the browser bridge is only available on Linux".to_string(),
+    ))
+}
+
+// Returns the manifest files written. Chromium-based browsers need the
+// extension's id; Firefox is set up either way.
+#[tauri::command]
+pub fn install_browser_bridge(chrome_extension_ids: Vec<String>) -> CommandResult<Vec<String>> {
+    let mut args = vec!["--install".to_string()];
+    args.extend(chrome_extension_ids);
+    run_host(&args)
+}
+
+// Returns the manifest files removed
+#[tauri::command]
+pub fn uninstall_browser_bridge() -> CommandResult<Vec<String>> {
+    run_host(&["--uninstall".to_string()])
+}
@@ -1,9 +1,15 @@
-use rusqlite::{Connection, Result as SqliteResult};
-use serde::{Deserialize, Serialize};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::Mutex;
 use tauri::State;
 use uuid::Uuid;
 
+use crate::error::{CommandResult, FocusLockError, OrNotFound};
+use crate::passphrase::PassphraseState;
+use crate::strict;
+
 // Data structures matching the TypeScript schema
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -24,13 +30,194 @@ pub struct InsertFavorite {
     pub icon_hint: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchKind {
+    Exe,
+    Package,
+    Lnk,
+    Path,
+    Regex,
+    // A website rather than a process; see domains.rs
+    Domain,
+}
+
+impl MatchKind {
+    pub const ALL: [MatchKind; 6] = [
+        MatchKind::Exe,
+        MatchKind::Package,
+        MatchKind::Lnk,
+        MatchKind::Path,
+        MatchKind::Regex,
+        MatchKind::Domain,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchKind::Exe => "exe",
+            MatchKind::Package => "package",
+            MatchKind::Lnk => "lnk",
+            MatchKind::Path => "path",
+            MatchKind::Regex => "regex",
+            MatchKind::Domain => "domain",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockMode {
+    Hard,
+    Soft,
+    // Suspend for the rest of the session instead of killing
+    Freeze,
+}
+
+impl BlockMode {
+    pub const ALL: [BlockMode; 3] = [BlockMode::Hard, BlockMode::Soft, BlockMode::Freeze];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockMode::Hard => "hard",
+            BlockMode::Soft => "soft",
+            BlockMode::Freeze => "freeze",
+        }
+    }
+}
+
+// How a rule set is applied: block what the rules match, or block every GUI
+// app they don't match
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListMode {
+    Blocklist,
+    Allowlist,
+}
+
+impl ListMode {
+    pub const ALL: [ListMode; 2] = [ListMode::Blocklist, ListMode::Allowlist];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListMode::Blocklist => "blocklist",
+            ListMode::Allowlist => "allowlist",
+        }
+    }
+}
+
+// These enums are stored as their lowercase names; parsing is exact so a value
+// like "Hard" is rejected instead of being saved as a rule that never fires.
+// Paths are spelled out so other modules can use it on their own enums.
+macro_rules! text_enum {
+    ($ty:ident) => {
+        impl std::fmt::Display for $ty {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl std::str::FromStr for $ty {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $ty::ALL
+                    .into_iter()
+                    .find(|v| v.as_str() == s)
+                    .ok_or_else(|| {
+                        let allowed: Vec<&str> = $ty::ALL.iter().map(|v| v.as_str()).collect();
+                        format!("'{}' is not one of: {}", s, allowed.join(", "))
+                    })
+            }
+        }
+
+        impl rusqlite::types::ToSql for $ty {
+            fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+                Ok(rusqlite::types::ToSqlOutput::from(self.as_str()))
+            }
+        }
+
+        impl rusqlite::types::FromSql for $ty {
+            fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+                value
+                    .as_str()?
+                    .parse()
+                    .map_err(|e: String| rusqlite::types::FromSqlError::Other(e.into()))
+            }
+        }
+    };
+}
+pub(crate) use text_enum;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStatus {
+    Scheduled,
+    Running,
+    Paused,
+    Completed,
+    Canceled,
+    // A scheduled session whose whole window passed before it could start
+    Skipped,
+}
+
+impl SessionStatus {
+    pub const ALL: [SessionStatus; 6] = [
+        SessionStatus::Scheduled,
+        SessionStatus::Running,
+        SessionStatus::Paused,
+        SessionStatus::Completed,
+        SessionStatus::Canceled,
+        SessionStatus::Skipped,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionStatus::Scheduled => "scheduled",
+            SessionStatus::Running => "running",
+            SessionStatus::Paused => "paused",
+            SessionStatus::Completed => "completed",
+            SessionStatus::Canceled => "canceled",
+            SessionStatus::Skipped => "skipped",
+        }
+    }
+}
+
+text_enum!(MatchKind);
+text_enum!(BlockMode);
+text_enum!(ListMode);
+text_enum!(SessionStatus);
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct BlockRule {
     pub id: String,
     pub app_id: String,
-    pub match_kind: String,
-    pub mode: String,
+    pub match_kind: MatchKind,
+    pub mode: BlockMode,
+    // None for rules in the default set
+    pub profile_id: Option<String>,
+}
+
+pub fn parse_rule_field<T: FromStr<Err = String>>(field: &str, value: &str) -> CommandResult<T> {
+    value.parse().map_err(|message| FocusLockError::validation(field, value, message))
+}
+
+// Processes can't be suspended on Windows, so a freeze rule would never fire
+pub fn parse_block_mode(field: &str, value: &str) -> CommandResult<BlockMode> {
+    let mode: BlockMode = parse_rule_field(field, value)?;
+    if cfg!(target_os = "windows") && mode == BlockMode::Freeze {
+        return Err(FocusLockError::validation(field, value, "Freezing apps isn't supported on Windows"));
+    }
+    Ok(mode)
+}
+
+fn validate_rule_pattern(app_id: &str, match_kind: MatchKind) -> CommandResult<()> {
+    if app_id.trim().is_empty() {
+        return Err(FocusLockError::validation("appId", app_id, "App id cannot be empty"));
+    }
+
+    crate::enforcement::validate_rule(app_id, match_kind)
+        .map_err(|message| FocusLockError::validation("appId", app_id, message))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -39,6 +226,7 @@ pub struct InsertBlockRule {
     pub app_id: String,
     pub match_kind: String,
     pub mode: String,
+    pub profile_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -47,6 +235,19 @@ pub struct UpdateBlockRule {
     pub app_id: Option<String>,
     pub match_kind: Option<String>,
     pub mode: Option<String>,
+    // Some(None) moves the rule to the default set
+    #[serde(default, deserialize_with = "double_option")]
+    pub profile_id: Option<Option<String>>,
+}
+
+// For optional fields that can also be cleared: a missing field stays None,
+// an explicit null becomes Some(None)
+pub fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -55,9 +256,11 @@ pub struct Session {
     pub id: String,
     pub start_utc: i64,
     pub end_utc: i64,
-    pub status: String,
+    pub status: SessionStatus,
     pub duration_secs: i32,
     pub remaining_secs: Option<i32>,
+    // Block profile whose rules apply; None uses the default set
+    pub profile_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -65,9 +268,10 @@ pub struct Session {
 pub struct InsertSession {
     pub start_utc: i64,
     pub end_utc: i64,
-    pub status: String,
+    pub status: SessionStatus,
     pub duration_secs: i32,
     pub remaining_secs: Option<i32>,
+    pub profile_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -75,9 +279,68 @@ pub struct InsertSession {
 pub struct UpdateSession {
     pub start_utc: Option<i64>,
     pub end_utc: Option<i64>,
-    pub status: Option<String>,
+    pub status: Option<SessionStatus>,
     pub duration_secs: Option<i32>,
     pub remaining_secs: Option<i32>,
+    pub profile_id: Option<String>,
+}
+
+// A stretch of a running session during which FocusLock wasn't enforcing
+// anything (app crashed, machine rebooted or slept)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInterruption {
+    pub id: String,
+    pub session_id: String,
+    pub started_utc: i64,
+    pub ended_utc: i64,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockEvent {
+    pub id: String,
+    pub timestamp_utc: i64,
+    pub session_id: Option<String>,
+    pub rule_id: Option<String>,
+    pub process_name: String,
+    pub exe_path: Option<String>,
+    pub pid: Option<i64>,
+    pub action: String,
+    // How many times this happened; more than 1 for aggregated DNS queries
+    pub count: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewBlockEvent {
+    pub timestamp_utc: i64,
+    pub session_id: Option<String>,
+    pub rule_id: Option<String>,
+    pub process_name: String,
+    pub exe_path: Option<String>,
+    pub pid: Option<i64>,
+    pub action: String,
+    pub count: i64,
+}
+
+// All filters are optional; `until_utc` is exclusive
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockEventFilter {
+    pub session_id: Option<String>,
+    pub rule_id: Option<String>,
+    pub since_utc: Option<i64>,
+    pub until_utc: Option<i64>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockEventPage {
+    pub events: Vec<BlockEvent>,
+    pub total: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -92,49 +355,10 @@ pub struct DbState {
 
 impl DbState {
     pub fn new(db_path: &str) -> SqliteResult<Self> {
-        let conn = Connection::open(db_path)?;
-        
-        // Create tables
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS favorites (
-                id TEXT PRIMARY KEY,
-                app_id TEXT NOT NULL,
-                display_name TEXT NOT NULL,
-                pinned_order INTEGER,
-                icon_hint TEXT
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS block_rules (
-                id TEXT PRIMARY KEY,
-                app_id TEXT NOT NULL,
-                match_kind TEXT NOT NULL,
-                mode TEXT NOT NULL
-            )",
-            [],
-        )?;
+        let mut conn = Connection::open(db_path)?;
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS sessions (
-                id TEXT PRIMARY KEY,
-                start_utc INTEGER NOT NULL,
-                end_utc INTEGER NOT NULL,
-                status TEXT NOT NULL,
-                duration_secs INTEGER NOT NULL,
-                remaining_secs INTEGER
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS settings (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            )",
-            [],
-        )?;
+        // Bring the schema up to date (backs up the file first if needed)
+        crate::migrations::migrate(&mut conn, Some(Path::new(db_path)))?;
 
         Ok(DbState {
             conn: Mutex::new(conn),
@@ -144,11 +368,10 @@ impl DbState {
 
 // Tauri Commands - Favorites
 #[tauri::command]
-pub fn get_favorites(db: State<DbState>) -> Result<Vec<Favorite>, String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+pub fn get_favorites(db: State<DbState>) -> CommandResult<Vec<Favorite>> {
+    let conn = db.conn.lock()?;
     let mut stmt = conn
-        .prepare("SELECT id, app_id, display_name, pinned_order, icon_hint FROM favorites ORDER BY pinned_order")
-        .map_err(|e| e.to_string())?;
+        .prepare("SELECT id, app_id, display_name, pinned_order, icon_hint FROM favorites ORDER BY pinned_order")?;
 
     let favorites = stmt
         .query_map([], |row| {
@@ -159,17 +382,15 @@ pub fn get_favorites(db: State<DbState>) -> Result<Vec<Favorite>, String> {
                 pinned_order: row.get(3)?,
                 icon_hint: row.get(4)?,
             })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
 
     Ok(favorites)
 }
 
 #[tauri::command]
-pub fn create_favorite(db: State<DbState>, favorite: InsertFavorite) -> Result<Favorite, String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+pub fn create_favorite(db: State<DbState>, favorite: InsertFavorite) -> CommandResult<Favorite> {
+    let conn = db.conn.lock()?;
     let id = Uuid::new_v4().to_string();
 
     conn.execute(
@@ -181,8 +402,7 @@ pub fn create_favorite(db: State<DbState>, favorite: InsertFavorite) -> Result<F
             &favorite.pinned_order,
             &favorite.icon_hint,
         ),
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
 
     Ok(Favorite {
         id,
@@ -194,241 +414,664 @@ pub fn create_favorite(db: State<DbState>, favorite: InsertFavorite) -> Result<F
 }
 
 #[tauri::command]
-pub fn delete_favorite(db: State<DbState>, id: String) -> Result<(), String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM favorites WHERE id = ?1", [&id])
-        .map_err(|e| e.to_string())?;
+pub fn delete_favorite(db: State<DbState>, id: String) -> CommandResult<()> {
+    let conn = db.conn.lock()?;
+    // Idempotent: deleting a favorite that is already gone is not an error
+    conn.execute("DELETE FROM favorites WHERE id = ?1", [&id])?;
     Ok(())
 }
 
 // Tauri Commands - Block Rules
-#[tauri::command]
-pub fn get_block_rules(db: State<DbState>) -> Result<Vec<BlockRule>, String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    let mut stmt = conn
-        .prepare("SELECT id, app_id, match_kind, mode FROM block_rules")
-        .map_err(|e| e.to_string())?;
+fn rule_from_row(row: &rusqlite::Row) -> SqliteResult<BlockRule> {
+    Ok(BlockRule {
+        id: row.get(0)?,
+        app_id: row.get(1)?,
+        match_kind: row.get(2)?,
+        mode: row.get(3)?,
+        profile_id: row.get(4)?,
+    })
+}
 
+// The rules enforced for a session with `profile_id` (None = default set)
+pub fn find_block_rules(conn: &Connection, profile_id: Option<&str>) -> SqliteResult<Vec<BlockRule>> {
+    let mut stmt = conn
+        .prepare("SELECT id, app_id, match_kind, mode, profile_id FROM block_rules WHERE profile_id IS ?1")?;
     let rules = stmt
-        .query_map([], |row| {
-            Ok(BlockRule {
-                id: row.get(0)?,
-                app_id: row.get(1)?,
-                match_kind: row.get(2)?,
-                mode: row.get(3)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
-
+        .query_map([profile_id], rule_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
     Ok(rules)
 }
 
+// One profile's rules, or the default set's when `profile_id` is None
+#[tauri::command]
+pub fn get_block_rules(db: State<DbState>, profile_id: Option<String>) -> CommandResult<Vec<BlockRule>> {
+    let conn = db.conn.lock()?;
+    Ok(find_block_rules(&conn, profile_id.as_deref())?)
+}
+
 #[tauri::command]
-pub fn create_block_rule(db: State<DbState>, rule: InsertBlockRule) -> Result<BlockRule, String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+pub fn create_block_rule(
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    rule: InsertBlockRule,
+    unlock_token: Option<String>,
+) -> CommandResult<BlockRule> {
+    let match_kind: MatchKind = parse_rule_field("matchKind", &rule.match_kind)?;
+    let mode = parse_block_mode("mode", &rule.mode)?;
+    validate_rule_pattern(&rule.app_id, match_kind)?;
+
+    let conn = db.conn.lock()?;
+    crate::profiles::ensure_exists(&conn, rule.profile_id.as_deref())?;
+
+    // Every allow rule lets one more app through; domain rules always block
+    if match_kind != MatchKind::Domain
+        && crate::profiles::list_mode(&conn, rule.profile_id.as_deref())? == ListMode::Allowlist
+    {
+        strict::ensure_unlocked(&conn, "add an app to an allowlist")?;
+        passphrase.ensure_authorized(&conn, unlock_token.as_deref(), "add an app to an allowlist")?;
+    }
+
     let id = Uuid::new_v4().to_string();
 
     conn.execute(
-        "INSERT INTO block_rules (id, app_id, match_kind, mode) VALUES (?1, ?2, ?3, ?4)",
-        (&id, &rule.app_id, &rule.match_kind, &rule.mode),
-    )
-    .map_err(|e| e.to_string())?;
+        "INSERT INTO block_rules (id, app_id, match_kind, mode, profile_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+        (&id, &rule.app_id, &match_kind, &mode, &rule.profile_id),
+    )?;
 
     Ok(BlockRule {
         id,
         app_id: rule.app_id,
-        match_kind: rule.match_kind,
-        mode: rule.mode,
+        match_kind,
+        mode,
+        profile_id: rule.profile_id,
     })
 }
 
+// Retargeting a rule or moving it to another profile drops protection from
+// whatever it matched before
+fn rule_loosened(previous: &BlockRule, updated: &BlockRule) -> bool {
+    updated.app_id != previous.app_id
+        || updated.match_kind != previous.match_kind
+        || updated.profile_id != previous.profile_id
+        || (previous.mode != BlockMode::Soft && updated.mode == BlockMode::Soft)
+}
+
 #[tauri::command]
-pub fn update_block_rule(db: State<DbState>, id: String, updates: UpdateBlockRule) -> Result<BlockRule, String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+pub fn update_block_rule(
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    id: String,
+    updates: UpdateBlockRule,
+    unlock_token: Option<String>,
+) -> CommandResult<BlockRule> {
+    // Reject bad values before touching the database
+    let match_kind: Option<MatchKind> = updates.match_kind.as_deref().map(|v| parse_rule_field("matchKind", v)).transpose()?;
+    let mode = updates.mode.as_deref().map(|v| parse_block_mode("mode", v)).transpose()?;
+
+    let conn = db.conn.lock()?;
 
     // First, get the current rule
     let mut stmt = conn
-        .prepare("SELECT id, app_id, match_kind, mode FROM block_rules WHERE id = ?1")
-        .map_err(|e| e.to_string())?;
+        .prepare("SELECT id, app_id, match_kind, mode, profile_id FROM block_rules WHERE id = ?1")?;
     
     let mut current_rule = stmt
-        .query_row([&id], |row| {
-            Ok(BlockRule {
-                id: row.get(0)?,
-                app_id: row.get(1)?,
-                match_kind: row.get(2)?,
-                mode: row.get(3)?,
-            })
-        })
-        .map_err(|e| e.to_string())?;
+        .query_row([&id], rule_from_row)
+        .or_not_found("block rule", &id)?;
+    let previous = current_rule.clone();
 
     // Update only the fields that are provided
     if let Some(app_id) = updates.app_id {
         current_rule.app_id = app_id;
     }
-    if let Some(match_kind) = updates.match_kind {
+    if let Some(match_kind) = match_kind {
         current_rule.match_kind = match_kind;
     }
-    if let Some(mode) = updates.mode {
+    if let Some(mode) = mode {
         current_rule.mode = mode;
     }
+    if let Some(profile_id) = updates.profile_id {
+        crate::profiles::ensure_exists(&conn, profile_id.as_deref())?;
+        current_rule.profile_id = profile_id;
+    }
+
+    validate_rule_pattern(&current_rule.app_id, current_rule.match_kind)?;
+
+    if rule_loosened(&previous, &current_rule) {
+        strict::ensure_unlocked(&conn, "loosen a block rule")?;
+        passphrase.ensure_authorized(&conn, unlock_token.as_deref(), "loosen a block rule")?;
+    }
 
     // Save the updated rule
     conn.execute(
-        "UPDATE block_rules SET app_id = ?1, match_kind = ?2, mode = ?3 WHERE id = ?4",
-        (&current_rule.app_id, &current_rule.match_kind, &current_rule.mode, &id),
-    )
-    .map_err(|e| e.to_string())?;
+        "UPDATE block_rules SET app_id = ?1, match_kind = ?2, mode = ?3, profile_id = ?4 WHERE id = ?5",
+        (&current_rule.app_id, &current_rule.match_kind, &current_rule.mode, &current_rule.profile_id, &id),
+    )?;
 
     Ok(current_rule)
 }
 
 #[tauri::command]
-pub fn delete_block_rule(db: State<DbState>, id: String) -> Result<(), String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    conn.execute("DELETE FROM block_rules WHERE id = ?1", [&id])
-        .map_err(|e| e.to_string())?;
+pub fn delete_block_rule(
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    id: String,
+    unlock_token: Option<String>,
+) -> CommandResult<()> {
+    let conn = db.conn.lock()?;
+    strict::ensure_unlocked(&conn, "delete a block rule")?;
+    passphrase.ensure_authorized(&conn, unlock_token.as_deref(), "delete a block rule")?;
+    conn.execute("DELETE FROM block_rules WHERE id = ?1", [&id])?;
     Ok(())
 }
 
 // Tauri Commands - Sessions
-#[tauri::command]
-pub fn get_sessions(db: State<DbState>) -> Result<Vec<Session>, String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    let mut stmt = conn
-        .prepare("SELECT id, start_utc, end_utc, status, duration_secs, remaining_secs FROM sessions ORDER BY start_utc DESC")
-        .map_err(|e| e.to_string())?;
+fn session_from_row(row: &rusqlite::Row) -> SqliteResult<Session> {
+    Ok(Session {
+        id: row.get(0)?,
+        start_utc: row.get(1)?,
+        end_utc: row.get(2)?,
+        status: row.get(3)?,
+        duration_secs: row.get(4)?,
+        remaining_secs: row.get(5)?,
+        profile_id: row.get(6)?,
+    })
+}
 
-    let sessions = stmt
-        .query_map([], |row| {
-            Ok(Session {
-                id: row.get(0)?,
-                start_utc: row.get(1)?,
-                end_utc: row.get(2)?,
-                status: row.get(3)?,
-                duration_secs: row.get(4)?,
-                remaining_secs: row.get(5)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+pub fn find_session(conn: &Connection, id: &str) -> SqliteResult<Session> {
+    conn.query_row(
+        "SELECT id, start_utc, end_utc, status, duration_secs, remaining_secs, profile_id FROM sessions WHERE id = ?1",
+        [id],
+        session_from_row,
+    )
+}
 
+pub fn find_sessions_with_status(conn: &Connection, statuses: &[SessionStatus]) -> SqliteResult<Vec<Session>> {
+    let wanted = statuses.iter().map(|s| format!("'{}'", s.as_str())).collect::<Vec<_>>().join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, start_utc, end_utc, status, duration_secs, remaining_secs, profile_id FROM sessions WHERE status IN ({}) ORDER BY start_utc DESC",
+        wanted
+    ))?;
+    let sessions = stmt
+        .query_map([], session_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
     Ok(sessions)
 }
 
-#[tauri::command]
-pub fn create_session(db: State<DbState>, session: InsertSession) -> Result<Session, String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    let id = Uuid::new_v4().to_string();
+// Writes every mutable field of a session back in one statement
+pub fn save_session(conn: &Connection, session: &Session) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE sessions SET start_utc = ?1, end_utc = ?2, status = ?3, duration_secs = ?4, remaining_secs = ?5 WHERE id = ?6",
+        (
+            session.start_utc,
+            session.end_utc,
+            session.status,
+            session.duration_secs,
+            session.remaining_secs,
+            &session.id,
+        ),
+    )?;
+    Ok(())
+}
 
+pub fn insert_session(conn: &Connection, session: &Session) -> SqliteResult<()> {
     conn.execute(
-        "INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs, remaining_secs) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        "INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs, remaining_secs, profile_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
         (
-            &id,
-            &session.start_utc,
-            &session.end_utc,
-            &session.status,
-            &session.duration_secs,
-            &session.remaining_secs,
+            &session.id,
+            session.start_utc,
+            session.end_utc,
+            session.status,
+            session.duration_secs,
+            session.remaining_secs,
+            &session.profile_id,
         ),
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
+    Ok(())
+}
 
-    Ok(Session {
-        id,
+#[tauri::command]
+pub fn get_sessions(db: State<DbState>) -> CommandResult<Vec<Session>> {
+    let conn = db.conn.lock()?;
+    let mut stmt = conn
+        .prepare("SELECT id, start_utc, end_utc, status, duration_secs, remaining_secs, profile_id FROM sessions ORDER BY start_utc DESC")?;
+
+    let sessions = stmt
+        .query_map([], session_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(sessions)
+}
+
+#[tauri::command]
+pub fn create_session(db: State<DbState>, session: InsertSession) -> CommandResult<Session> {
+    let conn = db.conn.lock()?;
+    crate::profiles::ensure_exists(&conn, session.profile_id.as_deref())?;
+
+    let session = Session {
+        id: Uuid::new_v4().to_string(),
         start_utc: session.start_utc,
         end_utc: session.end_utc,
         status: session.status,
         duration_secs: session.duration_secs,
         remaining_secs: session.remaining_secs,
-    })
+        profile_id: session.profile_id,
+    };
+    insert_session(&conn, &session)?;
+
+    Ok(session)
+}
+
+// Rejects updates that would end an active session early or weaken it while
+// strict mode, the passphrase or the early end cooldown forbids that
+fn check_session_update(
+    conn: &Connection,
+    passphrase: &PassphraseState,
+    unlock_token: Option<&str>,
+    current: &Session,
+    updates: &UpdateSession,
+    now: i64,
+) -> CommandResult<()> {
+    // Status changes follow the same rules as the session engine commands
+    if let Some(status) = updates.status {
+        crate::session::check_transition(current.status, status)?;
+    }
+
+    // Ending an active session early or giving it less time to run
+    let active = matches!(current.status, SessionStatus::Running | SessionStatus::Paused);
+    let shortened = updates.end_utc.is_some_and(|end| end < current.end_utc)
+        || updates.duration_secs.is_some_and(|d| d < current.duration_secs)
+        || updates.remaining_secs.is_some_and(|r| current.remaining_secs.is_some_and(|cur| r < cur));
+    // Completing a session whose time is up is the timer finishing, as in
+    // `session::apply`
+    let ends_early = match updates.status {
+        Some(SessionStatus::Canceled | SessionStatus::Paused) => true,
+        Some(SessionStatus::Completed) => crate::session::remaining_secs(current, now) > 0,
+        _ => false,
+    };
+    if active && (shortened || ends_early) {
+        strict::ensure_unlocked(conn, "end or shorten the current session")?;
+        passphrase.ensure_authorized(conn, unlock_token, "end or shorten the current session")?;
+        // Same friction as cancel_session: only through an early end request
+        crate::early_end::ensure_no_cooldown(conn)?;
+    }
+
+    if let Some(profile_id) = &updates.profile_id {
+        crate::profiles::ensure_exists(conn, Some(profile_id))?;
+        if active && current.profile_id.as_ref() != Some(profile_id) {
+            strict::ensure_unlocked(conn, "switch the current session's profile")?;
+            passphrase.ensure_authorized(conn, unlock_token, "switch the current session's profile")?;
+        }
+    }
+
+    Ok(())
 }
 
 #[tauri::command]
-pub fn update_session(db: State<DbState>, id: String, updates: UpdateSession) -> Result<Session, String> {
+pub fn update_session(
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    id: String,
+    updates: UpdateSession,
+    unlock_token: Option<String>,
+) -> CommandResult<Session> {
     {
-        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let conn = db.conn.lock()?;
+        let current = find_session(&conn, &id).or_not_found("session", &id)?;
+
+        if updates.status.is_some_and(|status| crate::session::is_repeat_finish(current.status, status)) {
+            return Ok(current);
+        }
+        check_session_update(&conn, &passphrase, unlock_token.as_deref(), &current, &updates, crate::session::now_utc())?;
 
         // Build UPDATE statements for each field separately to handle Options
         if let Some(start_utc) = updates.start_utc {
-            conn.execute("UPDATE sessions SET start_utc = ?1 WHERE id = ?2", (start_utc, &id))
-                .map_err(|e| e.to_string())?;
+            conn.execute("UPDATE sessions SET start_utc = ?1 WHERE id = ?2", (start_utc, &id))?;
         }
         if let Some(end_utc) = updates.end_utc {
-            conn.execute("UPDATE sessions SET end_utc = ?1 WHERE id = ?2", (end_utc, &id))
-                .map_err(|e| e.to_string())?;
+            conn.execute("UPDATE sessions SET end_utc = ?1 WHERE id = ?2", (end_utc, &id))?;
         }
         if let Some(status) = updates.status {
-            conn.execute("UPDATE sessions SET status = ?1 WHERE id = ?2", (status, &id))
-                .map_err(|e| e.to_string())?;
+            conn.execute("UPDATE sessions SET status = ?1 WHERE id = ?2", (status, &id))?;
         }
         if let Some(duration_secs) = updates.duration_secs {
-            conn.execute("UPDATE sessions SET duration_secs = ?1 WHERE id = ?2", (duration_secs, &id))
-                .map_err(|e| e.to_string())?;
+            conn.execute("UPDATE sessions SET duration_secs = ?1 WHERE id = ?2", (duration_secs, &id))?;
         }
         if let Some(remaining_secs) = updates.remaining_secs {
-            conn.execute("UPDATE sessions SET remaining_secs = ?1 WHERE id = ?2", (remaining_secs, &id))
-                .map_err(|e| e.to_string())?;
+            conn.execute("UPDATE sessions SET remaining_secs = ?1 WHERE id = ?2", (remaining_secs, &id))?;
+        }
+        if let Some(profile_id) = updates.profile_id {
+            conn.execute("UPDATE sessions SET profile_id = ?1 WHERE id = ?2", (profile_id, &id))?;
         }
     } // Release lock here
 
     // Fetch and return updated session with fresh lock
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    let session = conn
+    let conn = db.conn.lock()?;
+    let session = find_session(&conn, &id).or_not_found("session", &id)?;
+
+    Ok(session)
+}
+
+// Written by the session ticker so that after a crash we know when
+// enforcement actually stopped
+pub fn touch_session_heartbeat(conn: &Connection, session_id: &str, now: i64) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT INTO session_heartbeats (session_id, last_seen_utc) VALUES (?1, ?2)
+         ON CONFLICT(session_id) DO UPDATE SET last_seen_utc = ?2",
+        (session_id, now),
+    )?;
+    Ok(())
+}
+
+pub fn last_session_heartbeat(conn: &Connection, session_id: &str) -> SqliteResult<Option<i64>> {
+    match conn.query_row(
+        "SELECT last_seen_utc FROM session_heartbeats WHERE session_id = ?1",
+        [session_id],
+        |row| row.get(0),
+    ) {
+        Ok(last_seen) => Ok(Some(last_seen)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn insert_session_interruption(
+    conn: &Connection,
+    session_id: &str,
+    started_utc: i64,
+    ended_utc: i64,
+    reason: &str,
+) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT INTO session_interruptions (id, session_id, started_utc, ended_utc, reason) VALUES (?1, ?2, ?3, ?4, ?5)",
+        (Uuid::new_v4().to_string(), session_id, started_utc, ended_utc, reason),
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_session_interruptions(db: State<DbState>, session_id: String) -> CommandResult<Vec<SessionInterruption>> {
+    let conn = db.conn.lock()?;
+    let mut stmt = conn
+        .prepare("SELECT id, session_id, started_utc, ended_utc, reason FROM session_interruptions WHERE session_id = ?1 ORDER BY started_utc")?;
+
+    let interruptions = stmt
+        .query_map([&session_id], |row| {
+            Ok(SessionInterruption {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                started_utc: row.get(2)?,
+                ended_utc: row.get(3)?,
+                reason: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(interruptions)
+}
+
+// Tauri Commands - Block Events
+pub fn record_block_events(db: &DbState, events: &[NewBlockEvent]) -> CommandResult<()> {
+    let mut conn = db.conn.lock()?;
+    let tx = conn.transaction()?;
+
+    for event in events {
+        tx.execute(
+            "INSERT INTO block_events (id, timestamp_utc, session_id, rule_id, process_name, exe_path, pid, action, count)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            (
+                Uuid::new_v4().to_string(),
+                event.timestamp_utc,
+                &event.session_id,
+                &event.rule_id,
+                &event.process_name,
+                &event.exe_path,
+                event.pid,
+                &event.action,
+                event.count,
+            ),
+        )?;
+    }
+
+    Ok(tx.commit()?)
+}
+
+const BLOCK_EVENT_FILTER: &str = "(?1 IS NULL OR session_id = ?1)
+    AND (?2 IS NULL OR rule_id = ?2)
+    AND (?3 IS NULL OR timestamp_utc >= ?3)
+    AND (?4 IS NULL OR timestamp_utc < ?4)";
+
+#[tauri::command]
+pub fn get_block_events(db: State<DbState>, filter: Option<BlockEventFilter>) -> CommandResult<BlockEventPage> {
+    let filter = filter.unwrap_or_default();
+    let limit = filter.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = filter.offset.unwrap_or(0).max(0);
+    let params = (&filter.session_id, &filter.rule_id, filter.since_utc, filter.until_utc);
+
+    let conn = db.conn.lock()?;
+
+    let total = conn
         .query_row(
-            "SELECT id, start_utc, end_utc, status, duration_secs, remaining_secs FROM sessions WHERE id = ?1",
-            [&id],
+            &format!("SELECT COUNT(*) FROM block_events WHERE {}", BLOCK_EVENT_FILTER),
+            params,
+            |row| row.get(0),
+        )?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, timestamp_utc, session_id, rule_id, process_name, exe_path, pid, action, count
+             FROM block_events WHERE {} ORDER BY timestamp_utc DESC, rowid DESC LIMIT ?5 OFFSET ?6",
+            BLOCK_EVENT_FILTER
+        ))?;
+
+    let events = stmt
+        .query_map(
+            (&filter.session_id, &filter.rule_id, filter.since_utc, filter.until_utc, limit, offset),
             |row| {
-                Ok(Session {
+                Ok(BlockEvent {
                     id: row.get(0)?,
-                    start_utc: row.get(1)?,
-                    end_utc: row.get(2)?,
-                    status: row.get(3)?,
-                    duration_secs: row.get(4)?,
-                    remaining_secs: row.get(5)?,
+                    timestamp_utc: row.get(1)?,
+                    session_id: row.get(2)?,
+                    rule_id: row.get(3)?,
+                    process_name: row.get(4)?,
+                    exe_path: row.get(5)?,
+                    pid: row.get(6)?,
+                    action: row.get(7)?,
+                    count: row.get(8)?,
                 })
             },
-        )
-        .map_err(|e| e.to_string())?;
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
 
-    Ok(session)
+    Ok(BlockEventPage { events, total })
+}
+
+// Settings written by the frontend may be JSON-encoded strings ("\"10\"")
+pub fn setting_value(raw: &str) -> &str {
+    raw.trim().trim_matches('"')
+}
+
+// The value stored under `key`, without any JSON quoting
+pub fn get_setting(conn: &Connection, key: &str) -> SqliteResult<Option<String>> {
+    let value: Option<String> =
+        conn.query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0)).optional()?;
+    Ok(value.map(|v| setting_value(&v).to_string()))
 }
 
 // Tauri Commands - Settings
 #[tauri::command]
-pub fn get_settings(db: State<DbState>) -> Result<Vec<Setting>, String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+pub fn get_settings(db: State<DbState>) -> CommandResult<Vec<Setting>> {
+    let conn = db.conn.lock()?;
     let mut stmt = conn
-        .prepare("SELECT key, value FROM settings")
-        .map_err(|e| e.to_string())?;
+        .prepare("SELECT key, value FROM settings WHERE key NOT IN (?1, ?2, ?3)")?;
 
+    // The passphrase hash and lockout state never leave the backend
     let settings = stmt
-        .query_map([], |row| {
+        .query_map(crate::passphrase::RESERVED_KEYS, |row| {
             Ok(Setting {
                 key: row.get(0)?,
                 value: row.get(1)?,
             })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
 
     Ok(settings)
 }
 
+// What writing `value` to `key` would weaken, if anything. Compared the way
+// `get_setting` will read it back.
+fn setting_loosening(conn: &Connection, key: &str, value: &str) -> SqliteResult<Option<&'static str>> {
+    let value = setting_value(value);
+    let action = if key == strict::STRICT_MODE_KEY && value != "true" {
+        Some("turn off strict mode")
+    } else if key == crate::early_end::COOLDOWN_KEY && crate::early_end::shortens_cooldown(conn, value)? {
+        Some("shorten the early end cooldown")
+    } else if key == crate::profiles::ALLOWLIST_TERMINALS_KEY && value == "true" {
+        Some("exempt terminals from the allowlist")
+    } else if key == crate::hosts::HOSTS_PATH_KEY {
+        Some("change the hosts file path")
+    } else if key == crate::dns::DNS_ENABLED_KEY && value != "true" {
+        Some("turn off the DNS filter")
+    } else if key == crate::dns::DNS_LISTEN_KEY || key == crate::dns::DNS_UPSTREAM_KEY {
+        Some("change the DNS filter's addresses")
+    } else {
+        None
+    };
+    Ok(action)
+}
+
 #[tauri::command]
-pub fn upsert_setting(db: State<DbState>, key: String, value: String) -> Result<Setting, String> {
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+pub fn upsert_setting(
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    key: String,
+    value: String,
+    unlock_token: Option<String>,
+) -> CommandResult<Setting> {
+    if crate::passphrase::RESERVED_KEYS.contains(&key.as_str()) {
+        return Err(FocusLockError::validation("key", &key, "Use set_passphrase to change the passphrase"));
+    }
+
+    let conn = db.conn.lock()?;
+    crate::dns::validate_setting(&conn, &key, &value)?;
+
+    if let Some(action) = setting_loosening(&conn, &key, &value)? {
+        strict::ensure_unlocked(&conn, action)?;
+        passphrase.ensure_authorized(&conn, unlock_token.as_deref(), action)?;
+    }
 
     conn.execute(
         "INSERT INTO settings (key, value) VALUES (?1, ?2)
          ON CONFLICT(key) DO UPDATE SET value = ?2",
         (&key, &value),
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
 
     Ok(Setting { key, value })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked_conn() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+        conn.execute_batch(
+            "INSERT INTO settings (key, value) VALUES ('strictMode', 'true');
+             INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs) VALUES ('s1', 1000, 4600, 'running', 3600);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn no_updates() -> UpdateSession {
+        UpdateSession {
+            start_utc: None,
+            end_utc: None,
+            status: None,
+            duration_secs: None,
+            remaining_secs: None,
+            profile_id: None,
+        }
+    }
+
+    #[test]
+    fn strict_mode_allows_tightening_but_not_loosening() {
+        let conn = locked_conn();
+        let unlocked = PassphraseState::new();
+        let current = find_session(&conn, "s1").unwrap();
+        let locked = |result: CommandResult<()>| matches!(result, Err(FocusLockError::StrictModeLocked { .. }));
+
+        // Sessions: more time is fine, less time or ending isn't
+        let extend = UpdateSession { end_utc: Some(5000), ..no_updates() };
+        assert!(check_session_update(&conn, &unlocked, None, &current, &extend, 2000).is_ok());
+        let shorten = UpdateSession { end_utc: Some(3000), ..no_updates() };
+        assert!(locked(check_session_update(&conn, &unlocked, None, &current, &shorten, 2000)));
+        let cancel = UpdateSession { status: Some(SessionStatus::Canceled), ..no_updates() };
+        assert!(locked(check_session_update(&conn, &unlocked, None, &current, &cancel, 2000)));
+        let complete = UpdateSession { status: Some(SessionStatus::Completed), ..no_updates() };
+        assert!(locked(check_session_update(&conn, &unlocked, None, &current, &complete, 2000)));
+        // The client's own update when the timer runs out
+        assert!(check_session_update(&conn, &unlocked, None, &current, &complete, 4600).is_ok());
+
+        // Rules: hardening a rule is tightening, retargeting or softening isn't
+        let rule = BlockRule {
+            id: "r1".to_string(),
+            app_id: "steam".to_string(),
+            match_kind: MatchKind::Exe,
+            mode: BlockMode::Soft,
+            profile_id: None,
+        };
+        assert!(!rule_loosened(&rule, &BlockRule { mode: BlockMode::Hard, ..rule.clone() }));
+        let hard = BlockRule { mode: BlockMode::Hard, ..rule.clone() };
+        assert!(rule_loosened(&hard, &rule));
+        assert!(rule_loosened(&rule, &BlockRule { app_id: "lutris".to_string(), ..rule.clone() }));
+        assert!(rule_loosened(&rule, &BlockRule { profile_id: Some("p1".to_string()), ..rule.clone() }));
+
+        // Settings
+        assert_eq!(setting_loosening(&conn, "strictMode", "true").unwrap(), None);
+        assert!(setting_loosening(&conn, "strictMode", "false").unwrap().is_some());
+        assert_eq!(setting_loosening(&conn, "theme", "dark").unwrap(), None);
+        assert_eq!(setting_loosening(&conn, "strictMode", " \"true\" ").unwrap(), None);
+        assert!(setting_loosening(&conn, "allowlistTerminals", "\"true\"").unwrap().is_some());
+        assert!(setting_loosening(&conn, "allowlistTerminals", " true").unwrap().is_some());
+        assert_eq!(setting_loosening(&conn, "earlyEndCooldownMinutes", "30").unwrap(), None);
+        conn.execute("INSERT INTO settings (key, value) VALUES ('earlyEndCooldownMinutes', '10')", []).unwrap();
+        assert_eq!(setting_loosening(&conn, "earlyEndCooldownMinutes", "10").unwrap(), None);
+        assert!(setting_loosening(&conn, "earlyEndCooldownMinutes", "0").unwrap().is_some());
+
+        // Once the session is over the same updates go through
+        conn.execute("UPDATE sessions SET status = 'completed'", []).unwrap();
+        assert!(!strict::is_locked(&conn).unwrap());
+        assert!(strict::ensure_unlocked(&conn, "turn off strict mode").is_ok());
+    }
+
+    #[test]
+    fn ending_a_session_early_needs_the_passphrase() {
+        let conn = locked_conn();
+        conn.execute("UPDATE settings SET value = 'false' WHERE key = 'strictMode'", []).unwrap();
+        let hash = crate::passphrase::hash_passphrase("hunter2").unwrap();
+        conn.execute("INSERT INTO settings (key, value) VALUES (?1, ?2)", (crate::passphrase::PASSPHRASE_HASH_KEY, &hash)).unwrap();
+
+        let passphrase = PassphraseState::new();
+        let current = find_session(&conn, "s1").unwrap();
+        let cancel = UpdateSession { status: Some(SessionStatus::Canceled), ..no_updates() };
+        let err = check_session_update(&conn, &passphrase, None, &current, &cancel, 2000).unwrap_err();
+        assert_eq!(err.code(), "passphraseRequired");
+        let shorten = UpdateSession { end_utc: Some(3000), ..no_updates() };
+        let err = check_session_update(&conn, &passphrase, Some("stale"), &current, &shorten, 2000).unwrap_err();
+        assert_eq!(err.code(), "passphraseRequired");
+
+        let extend = UpdateSession { end_utc: Some(5000), ..no_updates() };
+        assert!(check_session_update(&conn, &passphrase, None, &current, &extend, 2000).is_ok());
+        let err = crate::session::apply(&conn, &passphrase, None, "s1", SessionStatus::Paused).unwrap_err();
+        assert_eq!(err.to_string(), "Enter the admin passphrase to pause the current session");
+    }
+
+    #[test]
+    fn rule_updates_tell_a_missing_profile_from_a_cleared_one() {
+        let parse = |json: &str| serde_json::from_str::<UpdateBlockRule>(json).unwrap().profile_id;
+        assert_eq!(parse(r#"{"mode": "hard"}"#), None);
+        assert_eq!(parse(r#"{"profileId": null}"#), Some(None));
+        assert_eq!(parse(r#"{"profileId": "p1"}"#), Some(Some("p1".to_string())));
+    }
+}
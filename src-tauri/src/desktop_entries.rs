@@ -0,0 +1,288 @@
+// Linux installed-app discovery from freedesktop .desktop entries
+//
+// Follows the XDG lookup order: $XDG_DATA_HOME first, then $XDG_DATA_DIRS,
+// then the Flatpak and Snap export dirs in case the session didn't add them.
+// The first entry seen for a desktop id wins, so user overrides (including
+// ones that hide an app) shadow the system copy.
+
+use freedesktop_desktop_entry::{get_languages_from_env, DesktopEntry, Iter};
+use std::collections::HashSet;
+use std::env;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesktopApp {
+    pub id: String,
+    pub name: String,
+    pub exec_path: Option<String>,
+    pub icon: Option<String>,
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME").filter(|h| !h.is_empty()).map(PathBuf::from)
+}
+
+// $XDG_DATA_HOME, $XDG_DATA_DIRS and the Flatpak export trees, in lookup order
+pub fn data_dirs() -> Vec<PathBuf> {
+    let home = home_dir();
+    let mut data_dirs = Vec::new();
+
+    match env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => data_dirs.push(PathBuf::from(dir)),
+        None => data_dirs.extend(home.as_ref().map(|h| h.join(".local/share"))),
+    }
+
+    let system_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    data_dirs.extend(system_dirs.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+
+    if let Some(home) = &home {
+        data_dirs.push(home.join(".local/share/flatpak/exports/share"));
+    }
+    data_dirs.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+    data_dirs.push(PathBuf::from("/usr/share"));
+
+    let mut seen = HashSet::new();
+    data_dirs.retain(|d| seen.insert(d.clone()));
+    data_dirs
+}
+
+fn application_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = data_dirs().into_iter().map(|d| d.join("applications")).collect();
+
+    // Snap exports desktop files directly rather than under share/applications
+    dirs.push(PathBuf::from("/var/lib/snapd/desktop/applications"));
+    dirs.retain(|d| d.is_dir());
+    dirs
+}
+
+// Desktop ids are the path below the applications dir with '/' → '-'
+fn desktop_id(dirs: &[PathBuf], path: &Path) -> String {
+    let relative = dirs
+        .iter()
+        .find_map(|d| path.strip_prefix(d).ok())
+        .unwrap_or(path);
+    relative.to_string_lossy().trim_end_matches(".desktop").replace('/', "-")
+}
+
+fn current_desktops() -> Vec<String> {
+    env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|d| !d.is_empty())
+        .map(|d| d.to_lowercase())
+        .collect()
+}
+
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(';')
+        .filter(|d| !d.is_empty())
+        .map(|d| d.trim().to_lowercase())
+        .collect()
+}
+
+// OnlyShowIn/NotShowIn are matched against $XDG_CURRENT_DESKTOP
+fn shown_in(only_show_in: Option<&str>, not_show_in: Option<&str>, desktops: &[String]) -> bool {
+    let only = split_list(only_show_in);
+    let not = split_list(not_show_in);
+
+    if !only.is_empty() && !desktops.iter().any(|d| only.contains(d)) {
+        return false;
+    }
+    !desktops.iter().any(|d| not.contains(d))
+}
+
+// Splits an Exec value into arguments, honouring the spec's double quoting
+fn exec_args(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            '\\' if in_quotes => current.extend(chars.next()),
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+    args
+}
+
+// The program an Exec line launches and its arguments, skipping an
+// `env VAR=value` prefix
+fn command_line(exec: &str) -> Vec<String> {
+    let mut args = exec_args(exec).into_iter().peekable();
+
+    if args.peek().is_some_and(|a| a.as_str() == "env" || a.ends_with("/env")) {
+        args.next();
+        while let Some(arg) = args.next_if(|a| a.contains('=') || a.starts_with('-')) {
+            // -u/--unset take the variable name as a separate argument
+            if arg == "-u" || arg == "--unset" {
+                args.next();
+            }
+        }
+    }
+    args.collect()
+}
+
+pub fn exec_program(exec: &str) -> Option<String> {
+    command_line(exec).into_iter().next().filter(|a| !a.is_empty() && !a.starts_with('%'))
+}
+
+// Flatpak and Snap apps launch through `flatpak run <app id>`, `snap run
+// <name>` or a /snap/bin wrapper, none of which is the app's own process.
+// Returns the app id for those.
+pub fn sandboxed_app_id(exec: &str) -> Option<String> {
+    let args = command_line(exec);
+    let (program, rest) = args.split_first()?;
+
+    if program.starts_with("/snap/bin/") {
+        return program.rsplit('/').next().filter(|name| !name.is_empty()).map(String::from);
+    }
+
+    let launcher = program.rsplit('/').next().unwrap_or(program);
+    if launcher != "flatpak" && launcher != "snap" {
+        return None;
+    }
+    let mut rest = rest.iter().skip_while(|a| a.as_str() != "run").skip(1);
+    rest.find(|a| !a.starts_with('-') && !a.starts_with('@') && !a.starts_with('%')).cloned()
+}
+
+// Resolve a bare program name through $PATH so path rules can match it
+fn resolve_program(program: &str) -> String {
+    if program.contains('/') {
+        return program.to_string();
+    }
+
+    env::var_os("PATH")
+        .and_then(|paths| {
+            env::split_paths(&paths)
+                .map(|dir| dir.join(program))
+                .find(|candidate| candidate.is_file())
+        })
+        .and_then(|p| p.to_str().map(String::from))
+        .unwrap_or_else(|| program.to_string())
+}
+
+pub fn installed_apps() -> Vec<DesktopApp> {
+    let dirs = application_dirs();
+    let locales = get_languages_from_env();
+    let desktops = current_desktops();
+
+    let mut seen_ids = HashSet::new();
+    let mut apps = Vec::new();
+
+    for path in Iter::new(dirs.clone().into_iter()) {
+        if path.extension().and_then(|e| e.to_str()) != Some("desktop") {
+            continue;
+        }
+
+        // First occurrence of an id wins even if it turns out to be hidden
+        let id = desktop_id(&dirs, &path);
+        if !seen_ids.insert(id.clone()) {
+            continue;
+        }
+
+        let Ok(entry) = DesktopEntry::from_path(path.clone(), Some(locales.as_slice())) else {
+            continue;
+        };
+
+        if entry.desktop_entry("Type").is_some_and(|t| t != "Application")
+            || entry.no_display()
+            || entry.desktop_entry("Hidden") == Some("true")
+            || !shown_in(entry.desktop_entry("OnlyShowIn"), entry.desktop_entry("NotShowIn"), &desktops)
+        {
+            continue;
+        }
+
+        let Some(name) = entry.name(locales.as_slice()).map(|n| n.to_string()).filter(|n| !n.trim().is_empty()) else {
+            continue;
+        };
+
+        let sandboxed = entry
+            .desktop_entry("X-Flatpak")
+            .or_else(|| entry.desktop_entry("X-SnapInstanceName"))
+            .map(String::from)
+            .or_else(|| entry.exec().and_then(sandboxed_app_id));
+
+        // The launcher's path would match every sandboxed app, and never the
+        // app's own process
+        let (id, exec_path) = match sandboxed {
+            Some(app_id) => (app_id, None),
+            None => (id, entry.exec().and_then(exec_program).map(|p| resolve_program(&p))),
+        };
+
+        apps.push(DesktopApp {
+            id,
+            name,
+            exec_path,
+            icon: entry.icon().map(String::from).filter(|i| !i.is_empty()),
+        });
+    }
+
+    apps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_program_strips_field_codes_and_env() {
+        assert_eq!(exec_program("firefox %u").as_deref(), Some("firefox"));
+        assert_eq!(
+            exec_program(r#""/opt/My App/app" --flag %F"#).as_deref(),
+            Some("/opt/My App/app")
+        );
+        assert_eq!(
+            exec_program("env GDK_BACKEND=x11 -u FOO /usr/bin/code --new-window").as_deref(),
+            Some("/usr/bin/code")
+        );
+        assert_eq!(exec_program("%U"), None);
+        assert_eq!(exec_program(""), None);
+    }
+
+    #[test]
+    fn flatpak_and_snap_launchers_give_the_app_id() {
+        assert_eq!(
+            sandboxed_app_id("/usr/bin/flatpak run --branch=stable --arch=x86_64 --command=firefox --file-forwarding org.mozilla.firefox @@u %u @@").as_deref(),
+            Some("org.mozilla.firefox")
+        );
+        assert_eq!(sandboxed_app_id("env BAMF_DESKTOP_FILE_HINT=/var/lib/snapd/desktop/applications/firefox_firefox.desktop /snap/bin/firefox %u").as_deref(), Some("firefox"));
+        assert_eq!(sandboxed_app_id("snap run spotify %U").as_deref(), Some("spotify"));
+        assert_eq!(sandboxed_app_id("/usr/bin/code --new-window"), None);
+        assert_eq!(sandboxed_app_id("flatpak"), None);
+    }
+
+    #[test]
+    fn show_in_filters_follow_current_desktop() {
+        let gnome = vec!["ubuntu".to_string(), "gnome".to_string()];
+
+        assert!(shown_in(None, None, &gnome));
+        assert!(shown_in(Some("GNOME;"), None, &gnome));
+        assert!(!shown_in(Some("KDE;XFCE;"), None, &gnome));
+        assert!(!shown_in(None, Some("GNOME;"), &gnome));
+        assert!(!shown_in(Some("KDE;"), None, &[]));
+    }
+}
@@ -0,0 +1,601 @@
+// Local DNS filter for domain rules
+//
+// An optional stub resolver on a loopback address. Queries for names covered
+// by the running session's domain rules (wildcards included) are answered
+// with 0.0.0.0 / :: for A/AAAA and an empty answer for anything else; all other
+// queries are relayed to the configured upstream. Unlike the hosts file this
+// handles `*.` rules, and the short TTL keeps browsers from caching blocks
+// past the session.
+//
+// Point the system (or a browser) at the listen address to use it. Blocked
+// queries are written to block_events with action `dns_blocked` and the
+// queried name as the process name, one row per name and rule every flush
+// with `count` holding how many queries it stands for.
+
+use rusqlite::{Connection, Result as SqliteResult};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::{self, DbState, NewBlockEvent};
+use crate::domains::{self, EnforcedDomains};
+use crate::error::{CommandResult, FocusLockError};
+use crate::session::now_utc;
+
+pub const DNS_ENABLED_KEY: &str = "dnsFilterEnabled";
+pub const DNS_LISTEN_KEY: &str = "dnsFilterListenAddress";
+pub const DNS_UPSTREAM_KEY: &str = "dnsFilterUpstream";
+
+pub const DNS_BLOCKED_ACTION: &str = "dns_blocked";
+
+const DEFAULT_LISTEN: &str = "127.0.0.1:53";
+const DEFAULT_UPSTREAM: &str = "1.1.1.1:53";
+const REFRESH_INTERVAL_SECS: u64 = 2;
+// How long the listener waits between checks of its stop flag
+const POLL_TIMEOUT: Duration = Duration::from_secs(1);
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(3);
+// Relayed queries waiting on the upstream at once; more are refused
+const MAX_IN_FLIGHT: usize = 64;
+const BLOCKED_TTL_SECS: u32 = 30;
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+const RCODE_SERVFAIL: u8 = 2;
+const RCODE_REFUSED: u8 = 5;
+
+// Parses "ip:port" or a bare ip, which gets `default_port`
+pub fn parse_address(value: &str, default_port: u16) -> Result<SocketAddr, String> {
+    let value = db::setting_value(value);
+    value
+        .parse::<SocketAddr>()
+        .or_else(|_| value.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, default_port)))
+        .map_err(|_| format!("'{}' is not an IP address with an optional port", value))
+}
+
+// Settings check for the listen address: only loopback, so FocusLock never
+// becomes an open resolver
+pub fn parse_listen_address(value: &str) -> Result<SocketAddr, String> {
+    let addr = parse_address(value, 53)?;
+    if !addr.ip().is_loopback() {
+        return Err(format!("The DNS filter can only listen on localhost, not {}", addr.ip()));
+    }
+    Ok(addr)
+}
+
+struct Config {
+    enabled: bool,
+    listen: SocketAddr,
+    upstream: SocketAddr,
+}
+
+fn load_config(conn: &Connection) -> SqliteResult<Config> {
+    let enabled = db::get_setting(conn, DNS_ENABLED_KEY)?.as_deref() == Some("true");
+    let listen = db::get_setting(conn, DNS_LISTEN_KEY)?
+        .and_then(|v| parse_listen_address(&v).ok())
+        .unwrap_or_else(|| DEFAULT_LISTEN.parse().unwrap());
+    let upstream = db::get_setting(conn, DNS_UPSTREAM_KEY)?
+        .and_then(|v| parse_address(&v, 53).ok())
+        // Relaying to itself would loop every query
+        .filter(|upstream| *upstream != listen)
+        .unwrap_or_else(|| DEFAULT_UPSTREAM.parse().unwrap());
+    Ok(Config { enabled, listen, upstream })
+}
+
+// Checked by upsert_setting for either address, against the other one as
+// it's currently configured
+pub fn validate_setting(conn: &Connection, key: &str, value: &str) -> CommandResult<()> {
+    let invalid = |message: String| FocusLockError::validation("value", value, message);
+    let config = load_config(conn)?;
+    let (listen, upstream) = if key == DNS_LISTEN_KEY {
+        (parse_listen_address(value).map_err(invalid)?, config.upstream)
+    } else if key == DNS_UPSTREAM_KEY {
+        (config.listen, parse_address(value, 53).map_err(invalid)?)
+    } else {
+        return Ok(());
+    };
+
+    if listen == upstream {
+        return Err(invalid(format!("The DNS filter can't relay to its own address {}", listen)));
+    }
+    Ok(())
+}
+
+// The first question of a query
+#[derive(Debug, PartialEq)]
+struct Question {
+    name: String,
+    qtype: u16,
+    // Offset just past the question in the packet
+    end: usize,
+}
+
+fn parse_question(packet: &[u8]) -> Option<Question> {
+    // Header, not a response, at least one question
+    if packet.len() < 12 || packet[2] & 0x80 != 0 || u16::from_be_bytes([packet[4], packet[5]]) == 0 {
+        return None;
+    }
+
+    let mut labels = Vec::new();
+    let mut pos = 12;
+    loop {
+        let len = *packet.get(pos)? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        // Queries never need compression pointers in the question
+        if len & 0xC0 != 0 {
+            return None;
+        }
+        let label = packet.get(pos..pos + len)?;
+        labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        pos += len;
+    }
+
+    let qtype = u16::from_be_bytes([*packet.get(pos)?, *packet.get(pos + 1)?]);
+    packet.get(pos + 3)?;
+
+    Some(Question {
+        name: labels.join("."),
+        qtype,
+        end: pos + 4,
+    })
+}
+
+// A reply echoing the query's id, flags and question, with an optional answer
+// record for that question
+fn response(packet: &[u8], question: &Question, rcode: u8, answer: Option<&[u8]>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(question.end + 32);
+    out.extend_from_slice(&packet[0..2]);
+    // QR, the query's opcode and RD; then RA and the rcode
+    out.push(0x80 | (packet[2] & 0x79));
+    out.push(0x80 | rcode);
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&(answer.is_some() as u16).to_be_bytes());
+    out.extend_from_slice(&[0, 0, 0, 0]);
+    out.extend_from_slice(&packet[12..question.end]);
+
+    if let Some(rdata) = answer {
+        // Name is a pointer back to the question
+        out.extend_from_slice(&[0xC0, 0x0C]);
+        out.extend_from_slice(&question.qtype.to_be_bytes());
+        out.extend_from_slice(&CLASS_IN.to_be_bytes());
+        out.extend_from_slice(&BLOCKED_TTL_SECS.to_be_bytes());
+        out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        out.extend_from_slice(rdata);
+    }
+    out
+}
+
+// Other record types get NODATA rather than NXDOMAIN, which resolvers may
+// cache as the whole name not existing, A/AAAA included
+fn blocked_response(packet: &[u8], question: &Question) -> Vec<u8> {
+    match question.qtype {
+        TYPE_A => response(packet, question, 0, Some(&[0; 4])),
+        TYPE_AAAA => response(packet, question, 0, Some(&[0; 16])),
+        _ => response(packet, question, 0, None),
+    }
+}
+
+fn forward(packet: &[u8], upstream: SocketAddr) -> io::Result<Vec<u8>> {
+    let local: SocketAddr = if upstream.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse().unwrap();
+    let socket = UdpSocket::bind(local)?;
+    socket.set_read_timeout(Some(UPSTREAM_TIMEOUT))?;
+    socket.connect(upstream)?;
+    socket.send(packet)?;
+
+    let mut buf = [0u8; 4096];
+    loop {
+        let len = socket.recv(&mut buf)?;
+        // Ignore anything that isn't the answer to this query
+        if len >= 12 && buf[0..2] == packet[0..2] {
+            return Ok(buf[..len].to_vec());
+        }
+    }
+}
+
+// Session, rule and queried name: repeated queries for one become one event
+type EventKey = (Option<String>, String, String);
+
+// State shared between the listener, its relay threads and the refresh loop
+struct Shared {
+    rules: RwLock<EnforcedDomains>,
+    upstream: RwLock<SocketAddr>,
+    pending_events: Mutex<HashMap<EventKey, NewBlockEvent>>,
+    blocked: AtomicU64,
+    forwarded: AtomicU64,
+    in_flight: AtomicUsize,
+}
+
+enum Reply {
+    Now(Vec<u8>),
+    Forward,
+    Drop,
+}
+
+impl Shared {
+    fn new(upstream: SocketAddr) -> Self {
+        Shared {
+            rules: RwLock::new(EnforcedDomains::default()),
+            upstream: RwLock::new(upstream),
+            pending_events: Mutex::new(HashMap::new()),
+            blocked: AtomicU64::new(0),
+            forwarded: AtomicU64::new(0),
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    fn reply(&self, packet: &[u8]) -> Reply {
+        let Some(question) = parse_question(packet) else {
+            return Reply::Drop;
+        };
+
+        let Ok(rules) = self.rules.read() else {
+            return Reply::Drop;
+        };
+        let Some((rule_id, _)) = rules.rules.iter().find(|(_, rule)| rule.matches(&question.name)) else {
+            return Reply::Forward;
+        };
+
+        self.blocked.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut events) = self.pending_events.lock() {
+            let key = (rules.session_id.clone(), rule_id.clone(), question.name.clone());
+            events
+                .entry(key)
+                .or_insert_with(|| NewBlockEvent {
+                    timestamp_utc: now_utc(),
+                    session_id: rules.session_id.clone(),
+                    rule_id: Some(rule_id.clone()),
+                    process_name: question.name.clone(),
+                    exe_path: None,
+                    pid: None,
+                    action: DNS_BLOCKED_ACTION.to_string(),
+                    count: 0,
+                })
+                .count += 1;
+        }
+        Reply::Now(blocked_response(packet, &question))
+    }
+
+    fn relay(self: &Arc<Self>, socket: &UdpSocket, packet: Vec<u8>, peer: SocketAddr) {
+        let (Ok(reply_socket), Ok(upstream)) = (socket.try_clone(), self.upstream.read().map(|u| *u)) else {
+            return;
+        };
+        if self.in_flight.fetch_add(1, Ordering::Relaxed) >= MAX_IN_FLIGHT {
+            self.in_flight.fetch_sub(1, Ordering::Relaxed);
+            if let Some(question) = parse_question(&packet) {
+                let _ = reply_socket.send_to(&response(&packet, &question, RCODE_REFUSED, None), peer);
+            }
+            return;
+        }
+
+        let shared = Arc::clone(self);
+        std::thread::spawn(move || {
+            let reply = match forward(&packet, upstream) {
+                Ok(reply) => {
+                    shared.forwarded.fetch_add(1, Ordering::Relaxed);
+                    Some(reply)
+                }
+                // Tell the client rather than leave it waiting for a retry
+                Err(_) => parse_question(&packet).map(|q| response(&packet, &q, RCODE_SERVFAIL, None)),
+            };
+            if let Some(reply) = reply {
+                let _ = reply_socket.send_to(&reply, peer);
+            }
+            shared.in_flight.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+}
+
+struct Server {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Server {
+    fn start(listen: SocketAddr, shared: Arc<Shared>) -> io::Result<Server> {
+        let socket = UdpSocket::bind(listen)?;
+        socket.set_read_timeout(Some(POLL_TIMEOUT))?;
+        let addr = socket.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let stop_flag = Arc::clone(&stop);
+        let handle = std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            while !stop_flag.load(Ordering::Relaxed) {
+                // Timeouts just re-check the stop flag; other errors (such as
+                // ICMP resets on Windows) only affect one datagram
+                let Ok((len, peer)) = socket.recv_from(&mut buf) else {
+                    continue;
+                };
+                let packet = &buf[..len];
+                match shared.reply(packet) {
+                    Reply::Now(reply) => {
+                        let _ = socket.send_to(&reply, peer);
+                    }
+                    Reply::Forward => shared.relay(&socket, packet.to_vec(), peer),
+                    Reply::Drop => {}
+                }
+            }
+        });
+
+        Ok(Server { addr, stop, handle })
+    }
+
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.handle.join();
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsFilterStatus {
+    // None while disabled or unable to bind
+    pub listen_address: Option<String>,
+    pub upstream: String,
+    pub blocked_queries: u64,
+    pub forwarded_queries: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct Control {
+    server: Option<Server>,
+    // The address the running server was asked to listen on
+    listen: Option<SocketAddr>,
+    last_error: Option<String>,
+}
+
+pub struct DnsFilter {
+    shared: Arc<Shared>,
+    control: Mutex<Control>,
+}
+
+impl DnsFilter {
+    pub fn new() -> Self {
+        DnsFilter {
+            shared: Arc::new(Shared::new(DEFAULT_UPSTREAM.parse().unwrap())),
+            control: Mutex::new(Control::default()),
+        }
+    }
+
+    // Picks up setting and rule changes, starts or stops the listener to
+    // match, and writes out blocked queries
+    pub fn refresh(&self, db: &DbState) {
+        let (config, enforced) = {
+            let Ok(conn) = db.conn.lock() else {
+                return;
+            };
+            match (load_config(&conn), domains::enforced_domains(&conn, now_utc())) {
+                (Ok(config), Ok(enforced)) => (config, enforced),
+                _ => return,
+            }
+        };
+
+        if let Ok(mut rules) = self.shared.rules.write() {
+            *rules = enforced;
+        }
+        if let Ok(mut upstream) = self.shared.upstream.write() {
+            *upstream = config.upstream;
+        }
+
+        if let Ok(mut control) = self.control.lock() {
+            self.apply_config(&mut control, config);
+        }
+
+        let events: Vec<NewBlockEvent> = match self.shared.pending_events.lock() {
+            Ok(mut pending) => pending.drain().map(|(_, event)| event).collect(),
+            Err(_) => return,
+        };
+        if !events.is_empty() {
+            if let Err(e) = db::record_block_events(db, &events) {
+                println!("[DNS] Failed to record {} blocked queries: {}", events.len(), e);
+            }
+        }
+    }
+
+    fn apply_config(&self, control: &mut Control, config: Config) {
+        let wanted = config.enabled.then_some(config.listen);
+
+        if control.server.is_some() && control.listen != wanted {
+            if let Some(server) = control.server.take() {
+                server.stop();
+                println!("[DNS] Stopped listening");
+            }
+            control.listen = None;
+        }
+
+        let Some(listen) = wanted else {
+            control.last_error = None;
+            return;
+        };
+        if control.server.is_some() {
+            return;
+        }
+
+        match Server::start(listen, Arc::clone(&self.shared)) {
+            Ok(server) => {
+                println!("[DNS] Listening on {}", server.addr);
+                control.server = Some(server);
+                control.listen = Some(listen);
+                control.last_error = None;
+            }
+            Err(e) => {
+                // Retried every refresh; only log when the failure changes
+                let message = format!("Failed to listen on {}: {}", listen, e);
+                if control.last_error.as_deref() != Some(message.as_str()) {
+                    println!("[DNS] {}", message);
+                    control.last_error = Some(message);
+                }
+            }
+        }
+    }
+}
+
+pub fn spawn_filter(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            // Stopping the listener joins its thread, so keep that off the
+            // async workers
+            let handle = app.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || {
+                let db: State<DbState> = handle.state();
+                let filter: State<DnsFilter> = handle.state();
+                filter.refresh(&db);
+            })
+            .await;
+
+            tokio::time::sleep(Duration::from_secs(REFRESH_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+// Tauri Commands - DNS filter
+#[tauri::command]
+pub fn get_dns_filter_status(filter: State<DnsFilter>) -> CommandResult<DnsFilterStatus> {
+    let control = filter.control.lock()?;
+    let upstream = *filter.shared.upstream.read()?;
+
+    Ok(DnsFilterStatus {
+        listen_address: control.server.as_ref().map(|s| s.addr.to_string()),
+        upstream: upstream.to_string(),
+        blocked_queries: filter.shared.blocked.load(Ordering::Relaxed),
+        forwarded_queries: filter.shared.forwarded.load(Ordering::Relaxed),
+        last_error: control.last_error.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(id: u16, name: &str, qtype: u16) -> Vec<u8> {
+        let mut packet = id.to_be_bytes().to_vec();
+        // RD set, one question
+        packet.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+        for label in name.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet
+    }
+
+    fn ask(client: &UdpSocket, server: SocketAddr, packet: &[u8]) -> Vec<u8> {
+        client.send_to(packet, server).unwrap();
+        let mut buf = [0u8; 512];
+        let (len, _) = client.recv_from(&mut buf).unwrap();
+        buf[..len].to_vec()
+    }
+
+    #[test]
+    fn the_upstream_cannot_be_the_listen_address() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+
+        // Against the default listen address 127.0.0.1:53
+        assert!(validate_setting(&conn, DNS_UPSTREAM_KEY, "127.0.0.1").is_err());
+        assert!(validate_setting(&conn, DNS_UPSTREAM_KEY, "127.0.0.1:5353").is_ok());
+
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, '\"127.0.0.1:5353\"'), (?2, '127.0.0.1:5353')",
+            [DNS_LISTEN_KEY, DNS_UPSTREAM_KEY],
+        )
+        .unwrap();
+        assert!(validate_setting(&conn, DNS_UPSTREAM_KEY, "127.0.0.1:5353").is_err());
+        assert!(validate_setting(&conn, DNS_UPSTREAM_KEY, "127.0.0.1").is_ok());
+
+        // Saved before this check existed: the filter relays to the default
+        let config = load_config(&conn).unwrap();
+        assert_eq!(config.upstream, DEFAULT_UPSTREAM.parse().unwrap());
+    }
+
+    #[test]
+    fn blocked_answers_echo_the_question() {
+        let packet = query(0xBEEF, "Ads.Example.com", TYPE_A);
+        let question = parse_question(&packet).unwrap();
+        assert_eq!(question.name, "ads.example.com");
+        assert_eq!(question.end, packet.len());
+
+        let reply = blocked_response(&packet, &question);
+        assert_eq!(&reply[0..2], &[0xBE, 0xEF]);
+        assert_eq!(reply[2] & 0x81, 0x81);
+        assert_eq!(reply[3] & 0x0F, 0);
+        assert_eq!(&reply[6..8], &[0, 1]);
+        assert_eq!(&reply[reply.len() - 6..], &[0, 4, 0, 0, 0, 0]);
+
+        let mx = query(7, "example.com", 15);
+        let reply = blocked_response(&mx, &parse_question(&mx).unwrap());
+        assert_eq!(reply[3] & 0x0F, 0);
+        assert_eq!(&reply[6..8], &[0, 0]);
+        assert_eq!(reply.len(), mx.len());
+
+        assert_eq!(parse_question(&reply), None);
+        assert_eq!(parse_question(&packet[..packet.len() - 1]), None);
+
+        assert!(parse_listen_address("127.0.0.1:5353").is_ok());
+        assert!(parse_listen_address("0.0.0.0").is_err());
+        assert_eq!(parse_address("\"9.9.9.9\"", 53), Ok("9.9.9.9:53".parse().unwrap()));
+    }
+
+    #[test]
+    fn blocks_matching_names_and_relays_the_rest_upstream() {
+        // Stand-in upstream that answers every query with its own id and a
+        // marker byte
+        let upstream = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = upstream.recv_from(&mut buf) {
+                let mut reply = buf[..len].to_vec();
+                reply[2] |= 0x80;
+                reply.push(0xAA);
+                let _ = upstream.send_to(&reply, peer);
+            }
+        });
+
+        let shared = Arc::new(Shared::new(upstream_addr));
+        *shared.rules.write().unwrap() = EnforcedDomains {
+            session_id: Some("s1".to_string()),
+            rules: vec![("r1".to_string(), domains::parse_domain_rule("*.example.com").unwrap())],
+        };
+        let server = Server::start("127.0.0.1:0".parse().unwrap(), Arc::clone(&shared)).unwrap();
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let blocked = ask(&client, server.addr, &query(1, "cdn.example.com", TYPE_AAAA));
+        assert_eq!(&blocked[0..2], &[0, 1]);
+        assert_eq!(&blocked[blocked.len() - 16..], &[0; 16]);
+        ask(&client, server.addr, &query(3, "cdn.example.com", TYPE_A));
+
+        let relayed = ask(&client, server.addr, &query(2, "rust-lang.org", TYPE_A));
+        assert_eq!(&relayed[0..2], &[0, 2]);
+        assert_eq!(relayed.last(), Some(&0xAA));
+
+        // Both blocked queries for the name are one pending event
+        assert_eq!(shared.blocked.load(Ordering::Relaxed), 2);
+        assert_eq!(shared.forwarded.load(Ordering::Relaxed), 1);
+        let events = shared.pending_events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        let event = events.values().next().unwrap();
+        assert_eq!(event.process_name, "cdn.example.com");
+        assert_eq!(event.rule_id.as_deref(), Some("r1"));
+        assert_eq!(event.session_id.as_deref(), Some("s1"));
+        assert_eq!(event.count, 2);
+        drop(events);
+
+        server.stop();
+    }
+}
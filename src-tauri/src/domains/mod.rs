@@ -0,0 +1,50 @@
+// Website rules
+//
+// A `domain` block rule names a site instead of a process. Its app_id is a
+// domain ("reddit.com") or a wildcard ("*.reddit.com"); a pasted URL is
+// reduced to its host, and a leading `www.` is dropped since the bare domain
+// and its `www.` variant are always blocked together.
+
+mod rule;
+
+pub use rule::{parse_domain_rule, DomainRule};
+
+use rusqlite::{Connection, Result as SqliteResult};
+
+use crate::db::{self, BlockMode, BlockRule, MatchKind, SessionStatus};
+
+// The valid domain rules in a rule set, keyed by rule id. Soft rules only
+// warn, which no domain blocker can do, and anything unparsable was saved
+// before validation; both are skipped.
+pub fn domain_rules(rules: &[BlockRule]) -> Vec<(String, DomainRule)> {
+    rules
+        .iter()
+        .filter(|rule| rule.match_kind == MatchKind::Domain && rule.mode != BlockMode::Soft)
+        .filter_map(|rule| parse_domain_rule(&rule.app_id).ok().map(|parsed| (rule.id.clone(), parsed)))
+        .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct EnforcedDomains {
+    pub session_id: Option<String>,
+    // (rule id, rule)
+    pub rules: Vec<(String, DomainRule)>,
+}
+
+// The running session's domain rules; empty while no session is enforcing
+// (none running, or a pomodoro session on a break)
+pub fn enforced_domains(conn: &Connection, now: i64) -> SqliteResult<EnforcedDomains> {
+    let running = db::find_sessions_with_status(conn, &[SessionStatus::Running])?;
+    let Some(session) = running.into_iter().next() else {
+        return Ok(EnforcedDomains::default());
+    };
+    if !crate::pomodoro::enforcing_with(conn, &session, now) {
+        return Ok(EnforcedDomains::default());
+    }
+
+    let rules = db::find_block_rules(conn, session.profile_id.as_deref())?;
+    Ok(EnforcedDomains {
+        session_id: Some(session.id),
+        rules: domain_rules(&rules),
+    })
+}
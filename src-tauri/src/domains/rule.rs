@@ -0,0 +1,102 @@
+// Parsing and matching of `domain` rule patterns
+//
+// Free of any database or app state, so the native messaging host can build
+// it on its own (see src/bin/focuslock-native-host).
+
+// Stand-ins for `*.` where only exact host names can be blocked
+const COMMON_SUBDOMAINS: &[&str] = &["m", "mobile", "app"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainRule {
+    pub domain: String,
+    // `*.domain`: also every subdomain
+    pub wildcard: bool,
+}
+
+fn valid_label(label: &str) -> bool {
+    (1..=63).contains(&label.len())
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+}
+
+pub fn parse_domain_rule(app_id: &str) -> Result<DomainRule, String> {
+    let lower = app_id.trim().to_lowercase();
+    let without_scheme = lower.split_once("://").map_or(lower.as_str(), |(_, rest)| rest);
+    let host = without_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or("")
+        .rsplit('@')
+        .next()
+        .unwrap_or("");
+    // Port, then the root label's trailing dot
+    let host = host.split(':').next().unwrap_or("").trim_end_matches('.');
+
+    let (wildcard, host) = match host.strip_prefix("*.") {
+        Some(rest) => (true, rest),
+        None => (false, host),
+    };
+    let domain = host.strip_prefix("www.").unwrap_or(host);
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    if labels.len() < 2 || domain.len() > 253 || !labels.iter().all(|l| valid_label(l)) {
+        return Err(format!(
+            "'{}' is not a domain; use something like example.com or *.example.com",
+            app_id.trim()
+        ));
+    }
+
+    Ok(DomainRule {
+        domain: domain.to_string(),
+        wildcard,
+    })
+}
+
+impl DomainRule {
+    // Exact host names covering this rule, for blockers without wildcards
+    pub fn host_names(&self) -> Vec<String> {
+        let mut names = vec![self.domain.clone(), format!("www.{}", self.domain)];
+        if self.wildcard {
+            names.extend(COMMON_SUBDOMAINS.iter().map(|sub| format!("{}.{}", sub, self.domain)));
+        }
+        names
+    }
+
+    // Whether a queried host name falls under this rule
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        name.strip_suffix(self.domain.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest == "www." || (self.wildcard && rest.ends_with('.')))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_domains_urls_and_wildcards() {
+        let rule = |domain: &str, wildcard| DomainRule {
+            domain: domain.to_string(),
+            wildcard,
+        };
+
+        assert_eq!(parse_domain_rule("Reddit.com"), Ok(rule("reddit.com", false)));
+        assert_eq!(parse_domain_rule("https://www.reddit.com/r/rust?x=1"), Ok(rule("reddit.com", false)));
+        assert_eq!(parse_domain_rule("*.news.ycombinator.com"), Ok(rule("news.ycombinator.com", true)));
+        assert_eq!(parse_domain_rule("user@example.org:8080."), Ok(rule("example.org", false)));
+
+        for bad in ["", "localhost", "*.", "exa mple.com", "-bad.com", "a..com", "*.*.com"] {
+            assert!(parse_domain_rule(bad).is_err(), "{:?} should be rejected", bad);
+        }
+
+        assert_eq!(rule("x.com", false).host_names(), vec!["x.com", "www.x.com"]);
+        assert_eq!(rule("x.com", true).host_names().len(), 2 + COMMON_SUBDOMAINS.len());
+
+        assert!(rule("x.com", false).matches("WWW.X.com."));
+        assert!(!rule("x.com", false).matches("api.x.com"));
+        assert!(rule("x.com", true).matches("a.b.x.com"));
+        assert!(!rule("x.com", true).matches("notx.com"));
+    }
+}
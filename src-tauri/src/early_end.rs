@@ -0,0 +1,336 @@
+// Cooldown-delayed unlock for ending a session early
+//
+// With a cooldown configured (`earlyEndCooldownMinutes`, off by default), an
+// active session can't be canceled, completed early, paused or shortened
+// directly, and the monitor can't be stopped under it.
+// `request_early_end` starts the cooldown while enforcement carries on, and
+// `confirm_early_end` cancels the session once it has elapsed. Every request
+// is kept with its outcome: confirmed, withdrawn, or session_ended when the
+// session finished on its own first.
+
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+use crate::db::{self, DbState, Session, SessionStatus};
+use crate::error::{CommandResult, FocusLockError, OrNotFound};
+use crate::passphrase::PassphraseState;
+use crate::session::{self, now_utc};
+
+pub const COOLDOWN_KEY: &str = "earlyEndCooldownMinutes";
+const DEFAULT_COOLDOWN_MINUTES: i64 = 0;
+const MAX_COOLDOWN_MINUTES: i64 = 24 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EarlyEndRequest {
+    pub id: String,
+    pub session_id: String,
+    pub reason: Option<String>,
+    pub requested_utc: i64,
+    pub unlocks_utc: i64,
+    pub resolved_utc: Option<i64>,
+    // None while pending
+    pub outcome: Option<String>,
+}
+
+const REQUEST_COLUMNS: &str = "id, session_id, reason, requested_utc, unlocks_utc, resolved_utc, outcome";
+
+fn request_from_row(row: &rusqlite::Row) -> SqliteResult<EarlyEndRequest> {
+    Ok(EarlyEndRequest {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        reason: row.get(2)?,
+        requested_utc: row.get(3)?,
+        unlocks_utc: row.get(4)?,
+        resolved_utc: row.get(5)?,
+        outcome: row.get(6)?,
+    })
+}
+
+pub fn parse_cooldown_minutes(value: Option<&str>) -> i64 {
+    value
+        .and_then(|v| db::setting_value(v).parse::<i64>().ok())
+        .unwrap_or(DEFAULT_COOLDOWN_MINUTES)
+        .clamp(0, MAX_COOLDOWN_MINUTES)
+}
+
+pub fn cooldown_secs(conn: &Connection) -> SqliteResult<i64> {
+    let value = db::get_setting(conn, COOLDOWN_KEY)?;
+    Ok(parse_cooldown_minutes(value.as_deref()) * 60)
+}
+
+// Fails unless ending early is allowed without going through a request
+pub fn ensure_no_cooldown(conn: &Connection) -> CommandResult<()> {
+    if cooldown_secs(conn)? > 0 {
+        return Err(FocusLockError::InvalidState(
+            "Ending or pausing a session early needs an unlock request: request an early end and confirm it after the cooldown"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn pending_request(conn: &Connection, session_id: &str) -> SqliteResult<Option<EarlyEndRequest>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM early_end_requests WHERE session_id = ?1 AND resolved_utc IS NULL",
+            REQUEST_COLUMNS
+        ),
+        [session_id],
+        request_from_row,
+    )
+    .optional()
+}
+
+fn find_request(conn: &Connection, id: &str) -> CommandResult<EarlyEndRequest> {
+    conn.query_row(
+        &format!("SELECT {} FROM early_end_requests WHERE id = ?1", REQUEST_COLUMNS),
+        [id],
+        request_from_row,
+    )
+    .or_not_found("early end request", id)
+}
+
+fn resolve(conn: &Connection, request: &mut EarlyEndRequest, outcome: &str, now: i64) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE early_end_requests SET resolved_utc = ?1, outcome = ?2 WHERE id = ?3",
+        (now, outcome, &request.id),
+    )?;
+    request.resolved_utc = Some(now);
+    request.outcome = Some(outcome.to_string());
+    Ok(())
+}
+
+fn ensure_pending(request: &EarlyEndRequest) -> CommandResult<()> {
+    match &request.outcome {
+        Some(outcome) => Err(FocusLockError::InvalidState(format!(
+            "This early end request is already closed ({})",
+            outcome
+        ))),
+        None => Ok(()),
+    }
+}
+
+// Closes pending requests whose session ended some other way. Called by the
+// session ticker.
+pub fn close_finished(conn: &Connection, now: i64) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE early_end_requests SET resolved_utc = ?1, outcome = 'session_ended'
+         WHERE resolved_utc IS NULL
+           AND session_id NOT IN (SELECT id FROM sessions WHERE status IN (?2, ?3))",
+        (now, SessionStatus::Running, SessionStatus::Paused),
+    )?;
+    Ok(())
+}
+
+pub fn request(conn: &Connection, session_id: &str, reason: Option<String>, now: i64) -> CommandResult<EarlyEndRequest> {
+    let session = db::find_session(conn, session_id).or_not_found("session", session_id)?;
+
+    if !matches!(session.status, SessionStatus::Running | SessionStatus::Paused) {
+        return Err(FocusLockError::InvalidState(format!(
+            "Only an active session can be ended early, this one is {}",
+            session.status
+        )));
+    }
+
+    // Asking again doesn't restart the clock
+    if let Some(pending) = pending_request(conn, session_id)? {
+        return Ok(pending);
+    }
+
+    let request = EarlyEndRequest {
+        id: Uuid::new_v4().to_string(),
+        session_id: session_id.to_string(),
+        reason: reason.filter(|r| !r.trim().is_empty()),
+        requested_utc: now,
+        unlocks_utc: now + cooldown_secs(conn)?,
+        resolved_utc: None,
+        outcome: None,
+    };
+
+    conn.execute(
+        &format!("INSERT INTO early_end_requests ({}) VALUES (?1, ?2, ?3, ?4, ?5, NULL, NULL)", REQUEST_COLUMNS),
+        (
+            &request.id,
+            &request.session_id,
+            &request.reason,
+            request.requested_utc,
+            request.unlocks_utc,
+        ),
+    )?;
+
+    Ok(request)
+}
+
+// Cancels the session once the cooldown is over and returns it with the
+// closed request
+pub fn confirm(
+    conn: &Connection,
+    passphrase: &PassphraseState,
+    unlock_token: Option<&str>,
+    request_id: &str,
+    now: i64,
+) -> CommandResult<(EarlyEndRequest, Session)> {
+    let mut request = find_request(conn, request_id)?;
+    ensure_pending(&request)?;
+    passphrase.ensure_authorized(conn, unlock_token, "end the current session early")?;
+
+    if now < request.unlocks_utc {
+        return Err(FocusLockError::InvalidState(format!(
+            "The cooldown has {} seconds left",
+            request.unlocks_utc - now
+        )));
+    }
+
+    let mut session = db::find_session(conn, &request.session_id).or_not_found("session", &request.session_id)?;
+    session::transition(&mut session, SessionStatus::Canceled, now)?;
+    db::save_session(conn, &session)?;
+    crate::pomodoro::close_open_interval(conn, &session.id, now, false)?;
+    resolve(conn, &mut request, "confirmed", now)?;
+
+    Ok((request, session))
+}
+
+pub fn withdraw(conn: &Connection, request_id: &str, now: i64) -> CommandResult<EarlyEndRequest> {
+    let mut request = find_request(conn, request_id)?;
+    ensure_pending(&request)?;
+    resolve(conn, &mut request, "withdrawn", now)?;
+    Ok(request)
+}
+
+// Tauri Commands - Early end
+#[tauri::command]
+pub fn request_early_end(db: State<DbState>, session_id: String, reason: Option<String>) -> CommandResult<EarlyEndRequest> {
+    request(&*db.conn.lock()?, &session_id, reason, now_utc())
+}
+
+// Strict mode doesn't apply: waiting out the cooldown is the sanctioned way
+// out of a locked session. A passphrase still has to be entered.
+#[tauri::command]
+pub fn confirm_early_end(
+    app: AppHandle,
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    request_id: String,
+    unlock_token: Option<String>,
+) -> CommandResult<EarlyEndRequest> {
+    let (request, session) = confirm(&*db.conn.lock()?, &passphrase, unlock_token.as_deref(), &request_id, now_utc())?;
+    session::emit_ended(&app, &session);
+    Ok(request)
+}
+
+#[tauri::command]
+pub fn withdraw_early_end(db: State<DbState>, request_id: String) -> CommandResult<EarlyEndRequest> {
+    withdraw(&*db.conn.lock()?, &request_id, now_utc())
+}
+
+#[tauri::command]
+pub fn get_early_end_requests(db: State<DbState>, session_id: String) -> CommandResult<Vec<EarlyEndRequest>> {
+    let conn = db.conn.lock()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM early_end_requests WHERE session_id = ?1 ORDER BY requested_utc",
+        REQUEST_COLUMNS
+    ))?;
+
+    let requests = stmt
+        .query_map([&session_id], request_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(requests)
+}
+
+// Lowering the cooldown mid-session is a way around it
+pub fn shortens_cooldown(conn: &Connection, value: &str) -> SqliteResult<bool> {
+    Ok(parse_cooldown_minutes(Some(value)) * 60 < cooldown_secs(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cooldown_setting_parses_leniently() {
+        assert_eq!(parse_cooldown_minutes(None), 0);
+        assert_eq!(parse_cooldown_minutes(Some("0")), 0);
+        assert_eq!(parse_cooldown_minutes(Some("\"25\"")), 25);
+        assert_eq!(parse_cooldown_minutes(Some("-5")), 0);
+        assert_eq!(parse_cooldown_minutes(Some("soon")), 0);
+    }
+
+    fn conn_with_session(cooldown_minutes: i64) -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+        conn.execute(
+            "INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs) VALUES ('s1', 0, 3600, 'running', 3600)",
+            [],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO settings (key, value) VALUES (?1, ?2)", (COOLDOWN_KEY, cooldown_minutes.to_string()))
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn confirm_waits_for_the_cooldown() {
+        let conn = conn_with_session(10);
+        let unlocked = PassphraseState::new();
+        assert!(ensure_no_cooldown(&conn).is_err());
+
+        let pending = request(&conn, "s1", Some("  ".to_string()), 100).unwrap();
+        assert_eq!((pending.reason.clone(), pending.unlocks_utc), (None, 700));
+
+        // Asking again returns the same request without restarting the clock
+        let again = request(&conn, "s1", Some("done early".to_string()), 300).unwrap();
+        assert_eq!((again.id.as_str(), again.unlocks_utc), (pending.id.as_str(), 700));
+
+        let err = confirm(&conn, &unlocked, None, &pending.id, 699).unwrap_err();
+        assert_eq!(err.to_string(), "The cooldown has 1 seconds left");
+        assert_eq!(db::find_session(&conn, "s1").unwrap().status, SessionStatus::Running);
+
+        let (confirmed, session) = confirm(&conn, &unlocked, None, &pending.id, 700).unwrap();
+        assert_eq!((confirmed.outcome.as_deref(), confirmed.resolved_utc), (Some("confirmed"), Some(700)));
+        assert_eq!(session.status, SessionStatus::Canceled);
+        assert_eq!(confirm(&conn, &unlocked, None, &pending.id, 800).unwrap_err().code(), "invalidState");
+        assert_eq!(request(&conn, "s1", None, 800).unwrap_err().code(), "invalidState");
+    }
+
+    #[test]
+    fn pausing_waits_for_the_cooldown_too() {
+        let conn = conn_with_session(10);
+        let unlocked = PassphraseState::new();
+        let err = session::apply(&conn, &unlocked, None, "s1", SessionStatus::Paused).unwrap_err();
+        assert_eq!(err.code(), "invalidState");
+        assert_eq!(db::find_session(&conn, "s1").unwrap().status, SessionStatus::Running);
+
+        conn.execute("UPDATE settings SET value = '0' WHERE key = ?1", [COOLDOWN_KEY]).unwrap();
+        let paused = session::apply(&conn, &unlocked, None, "s1", SessionStatus::Paused).unwrap();
+        assert_eq!(paused.status, SessionStatus::Paused);
+    }
+
+    #[test]
+    fn withdrawn_and_finished_requests_are_closed() {
+        let conn = conn_with_session(10);
+        let unlocked = PassphraseState::new();
+        let first = request(&conn, "s1", None, 100).unwrap();
+        let withdrawn = withdraw(&conn, &first.id, 200).unwrap();
+        assert_eq!(withdrawn.outcome.as_deref(), Some("withdrawn"));
+        assert_eq!(confirm(&conn, &unlocked, None, &first.id, 900).unwrap_err().code(), "invalidState");
+        assert_eq!(withdraw(&conn, "missing", 200).unwrap_err().code(), "notFound");
+
+        // A new request starts a fresh cooldown
+        let second = request(&conn, "s1", None, 300).unwrap();
+        assert_ne!(second.id, first.id);
+        assert_eq!(second.unlocks_utc, 900);
+
+        close_finished(&conn, 400).unwrap();
+        assert_eq!(find_request(&conn, &second.id).unwrap().outcome, None);
+
+        conn.execute("UPDATE sessions SET status = 'completed'", []).unwrap();
+        close_finished(&conn, 500).unwrap();
+        let closed = find_request(&conn, &second.id).unwrap();
+        assert_eq!((closed.outcome.as_deref(), closed.resolved_utc), (Some("session_ended"), Some(500)));
+        assert_eq!(find_request(&conn, &first.id).unwrap().outcome.as_deref(), Some("withdrawn"));
+    }
+}
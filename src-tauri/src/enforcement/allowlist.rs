@@ -0,0 +1,158 @@
+// Processes allowlist mode never blocks
+//
+// In allowlist mode every GUI process that no rule allows gets blocked, so
+// FocusLock itself, the desktop shell and the session plumbing behind it are
+// exempt regardless of the rules. Names are compared after the same
+// normalization as exe rules; a trailing `*` matches a prefix.
+
+use super::matcher::normalize_name;
+use super::ProcessInfo;
+
+#[cfg(target_os = "windows")]
+const PROTECTED: &[&str] = &[
+    // The app and its native messaging host
+    "focuslock*",
+    // FocusLock's own webview
+    "msedgewebview2",
+    "explorer",
+    "dwm",
+    "winlogon",
+    "csrss",
+    "sihost",
+    "fontdrvhost",
+    "ctfmon",
+    "lockapp",
+    "logonui",
+    "shellexperiencehost",
+    "shellhost",
+    "startmenuexperiencehost",
+    "searchhost",
+    "searchapp",
+    "textinputhost",
+    "applicationframehost",
+    "runtimebroker",
+    "securityhealthsystray",
+];
+
+#[cfg(not(target_os = "windows"))]
+const PROTECTED: &[&str] = &[
+    // The app and its native messaging host
+    "focuslock*",
+    // FocusLock's own webview
+    "webkitwebprocess",
+    "webkitnetworkprocess",
+    "webkitgpuprocess",
+    // Display servers and session managers
+    "xorg",
+    "xwayland",
+    "gnome-session*",
+    "ksmserver",
+    "systemd",
+    "dbus-daemon",
+    "dbus-broker*",
+    // Shells and window managers
+    "gnome-shell*",
+    "plasmashell",
+    "kwin_*",
+    "kded*",
+    "kglobalaccel*",
+    "mutter*",
+    "xfwm4",
+    "xfce4-panel",
+    "xfce4-session",
+    "xfdesktop",
+    "xfsettingsd",
+    "cinnamon*",
+    "muffin",
+    "marco",
+    "mate-panel",
+    "mate-session",
+    "budgie-*",
+    "openbox",
+    "i3",
+    "i3bar",
+    "sway",
+    "swaybar",
+    "hyprland",
+    "waybar",
+    "wayfire",
+    "labwc",
+    "niri",
+    // Desktop services apps depend on
+    "gsd-*",
+    "xdg-desktop-portal*",
+    "xdg-document-portal",
+    "xdg-permission-store",
+    "pipewire*",
+    "wireplumber",
+    "pulseaudio",
+    "at-spi*",
+    "ibus*",
+    "fcitx*",
+    "gvfs*",
+    "gnome-keyring-daemon",
+    "kwalletd*",
+    "polkit-*",
+    "evolution-*",
+    "tracker-miner-*",
+    "localsearch*",
+    // GNOME Shell's notification and portal helpers run under gjs
+    "gjs*",
+    "dconf-service",
+    "gpg-agent",
+    "ssh-agent",
+    "goa-*",
+    "nm-applet",
+    "blueman-applet",
+    "kdeconnectd",
+    "xembedsniproxy",
+    "gmenudbusmenuproxy",
+    "org_kde_powerdevil",
+];
+
+#[cfg(target_os = "windows")]
+const TERMINALS: &[&str] = &["windowsterminal", "openconsole", "conhost", "cmd", "powershell", "pwsh"];
+
+#[cfg(not(target_os = "windows"))]
+const TERMINALS: &[&str] = &[
+    "gnome-terminal*",
+    "kgx",
+    "ptyxis*",
+    "konsole",
+    "xfce4-terminal",
+    "mate-terminal",
+    "tilix",
+    "terminator",
+    "xterm",
+    "urxvt",
+    "alacritty",
+    "kitty",
+    "wezterm*",
+    "foot",
+    "footclient",
+    "ghostty",
+];
+
+fn matches_entry(entry: &str, name: &str) -> bool {
+    match entry.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == entry,
+    }
+}
+
+pub struct ProtectedList {
+    terminals: bool,
+}
+
+impl ProtectedList {
+    // Terminal emulators are only exempt when the user opts in
+    pub fn new(terminals: bool) -> Self {
+        ProtectedList { terminals }
+    }
+
+    pub fn contains(&self, process: &ProcessInfo) -> bool {
+        let name = normalize_name(&process.name);
+        let extra: &[&str] = if self.terminals { TERMINALS } else { &[] };
+        PROTECTED.iter().chain(extra).any(|entry| matches_entry(entry, &name))
+    }
+}
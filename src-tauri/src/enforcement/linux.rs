@@ -0,0 +1,67 @@
+use super::{ProcessInfo, ProcessSource};
+use crate::{desktop_entries, procfs};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+// Apps get installed and removed while FocusLock runs
+const DESKTOP_PROGRAMS_TTL: Duration = Duration::from_secs(5 * 60);
+
+// /proc walker restricted to the current user's processes
+pub struct ProcfsSource {
+    // File names of the programs .desktop entries launch; see ProcEntry::is_gui
+    desktop_programs: HashSet<String>,
+    loaded_at: Option<Instant>,
+}
+
+impl ProcfsSource {
+    pub fn new() -> Self {
+        ProcfsSource {
+            desktop_programs: HashSet::new(),
+            loaded_at: None,
+        }
+    }
+
+    fn refresh_desktop_programs(&mut self) {
+        if self.loaded_at.is_some_and(|at| at.elapsed() < DESKTOP_PROGRAMS_TTL) {
+            return;
+        }
+
+        self.desktop_programs = desktop_entries::installed_apps()
+            .into_iter()
+            .filter_map(|app| app.exec_path)
+            .filter_map(|path| path.rsplit('/').next().map(str::to_lowercase))
+            .filter(|name| !name.is_empty())
+            .collect();
+        self.loaded_at = Some(Instant::now());
+    }
+}
+
+impl ProcessSource for ProcfsSource {
+    fn processes(&mut self) -> Vec<ProcessInfo> {
+        self.refresh_desktop_programs();
+
+        procfs::list_user_processes()
+            .into_iter()
+            .map(|entry| ProcessInfo {
+                pid: entry.pid as u32,
+                name: entry.name(),
+                gui: entry.is_gui(&self.desktop_programs),
+                exe_path: entry.exe_path,
+                cmdline: entry.cmdline,
+                comm: Some(entry.comm),
+            })
+            .collect()
+    }
+
+    fn kill(&mut self, pid: u32) -> bool {
+        procfs::kill(pid as i32)
+    }
+
+    fn suspend(&mut self, pid: u32) -> bool {
+        procfs::stop(pid as i32)
+    }
+
+    fn resume(&mut self, pid: u32) -> bool {
+        procfs::cont(pid as i32)
+    }
+}
@@ -0,0 +1,172 @@
+// Pure rule matching: no OS calls, so every match_kind/mode combination can be
+// exercised against hand-built `ProcessInfo`s
+
+use regex::{Regex, RegexBuilder};
+
+use super::ProcessInfo;
+use crate::db::{BlockMode, BlockRule, MatchKind};
+
+// Which process field a `regex` rule is tested against, chosen by an optional
+// `name:`, `path:` or `cmdline:` prefix on the rule's app_id. Without a prefix
+// the pattern is tried against all three.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegexTarget {
+    Name,
+    Path,
+    Cmdline,
+    Any,
+}
+
+struct CompiledRule {
+    rule: BlockRule,
+    pattern: Option<(RegexTarget, Regex)>,
+}
+
+pub struct RuleMatcher {
+    rules: Vec<CompiledRule>,
+}
+
+fn parse_regex_rule(app_id: &str) -> Result<(RegexTarget, Regex), String> {
+    let (target, pattern) = if let Some(p) = app_id.strip_prefix("name:") {
+        (RegexTarget::Name, p)
+    } else if let Some(p) = app_id.strip_prefix("path:") {
+        (RegexTarget::Path, p)
+    } else if let Some(p) = app_id.strip_prefix("cmdline:") {
+        (RegexTarget::Cmdline, p)
+    } else {
+        (RegexTarget::Any, app_id)
+    };
+
+    if pattern.is_empty() {
+        return Err("Regex pattern cannot be empty".to_string());
+    }
+
+    // Case-insensitive like every other match kind; `(?-i)` opts out
+    let regex = RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| format!("Invalid regex '{}': {}", pattern, e))?;
+
+    Ok((target, regex))
+}
+
+// Checked by create_block_rule/update_block_rule so a bad pattern is rejected
+// up front instead of being silently skipped by the monitor
+pub fn validate_rule(app_id: &str, match_kind: MatchKind) -> Result<(), String> {
+    match match_kind {
+        MatchKind::Regex => parse_regex_rule(app_id).map(|_| ()),
+        MatchKind::Domain => crate::domains::parse_domain_rule(app_id).map(|_| ()),
+        _ => Ok(()),
+    }
+}
+
+// Lowercased file name with any `.exe` suffix removed, so Windows-style rules
+// (`Code.exe`) and Linux process names (`code`) compare on the same footing
+pub(super) fn normalize_name(name: &str) -> String {
+    let last_part = name.rsplit(&['\\', '/'][..]).next().unwrap_or("");
+    let lower = last_part.to_lowercase();
+    match lower.strip_suffix(".exe") {
+        Some(stem) => stem.to_string(),
+        None => lower,
+    }
+}
+
+impl RuleMatcher {
+    // Regexes are compiled once here for the whole rule set. Rules that fail
+    // to compile (e.g. saved before validation existed) are dropped with a log
+    // line rather than disabling enforcement for everything else. Domain
+    // rules never match a process and are left out.
+    pub fn new(rules: Vec<BlockRule>) -> Self {
+        let rules = rules
+            .into_iter()
+            .filter(|rule| rule.match_kind != MatchKind::Domain)
+            .filter_map(|rule| {
+                if rule.match_kind != MatchKind::Regex {
+                    return Some(CompiledRule { rule, pattern: None });
+                }
+                match parse_regex_rule(&rule.app_id) {
+                    Ok(pattern) => Some(CompiledRule { rule, pattern: Some(pattern) }),
+                    Err(e) => {
+                        println!("[Monitor] Skipping rule {}: {}", rule.id, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        RuleMatcher { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    fn regex_matches(target: RegexTarget, regex: &Regex, process: &ProcessInfo) -> bool {
+        let name = || regex.is_match(&process.name);
+        let path = || process.exe_path.as_deref().is_some_and(|p| regex.is_match(p));
+        let cmdline = || !process.cmdline.is_empty() && regex.is_match(&process.cmdline.join(" "));
+
+        match target {
+            RegexTarget::Name => name(),
+            RegexTarget::Path => path(),
+            RegexTarget::Cmdline => cmdline(),
+            RegexTarget::Any => name() || path() || cmdline(),
+        }
+    }
+
+    fn rule_matches(compiled: &CompiledRule, process: &ProcessInfo) -> bool {
+        let rule = &compiled.rule;
+
+        if let Some((target, regex)) = &compiled.pattern {
+            return Self::regex_matches(*target, regex, process);
+        }
+
+        let rule_name = normalize_name(&rule.app_id);
+        let process_name = normalize_name(&process.name);
+
+        match rule.match_kind {
+            MatchKind::Exe => {
+                !rule_name.is_empty()
+                    && (process_name == rule_name
+                        // comm is capped at 15 bytes; compare on the same footing
+                        || (rule_name.len() > 15
+                            && rule_name.is_char_boundary(15)
+                            && process.comm.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(&rule_name[..15]))))
+            }
+            MatchKind::Path => {
+                // Scripts and AppImages run under an interpreter/loader, so fall
+                // back to argv[0] when the exe path doesn't match
+                let needle = rule.app_id.to_lowercase();
+                process.exe_path.as_deref().is_some_and(|p| p.to_lowercase().contains(&needle))
+                    || process.cmdline.first().is_some_and(|arg0| arg0.to_lowercase().contains(&needle))
+            }
+            // Equivalent to the old `"<name>.exe".contains("<rule>.exe")` check
+            _ => !rule_name.is_empty() && process_name.ends_with(&rule_name),
+        }
+    }
+
+    // Strictest matching rule for a process: hard beats freeze beats soft, so
+    // a process covered by several rules gets the harshest treatment
+    pub fn find(&self, process: &ProcessInfo) -> Option<&BlockRule> {
+        let strictness = |mode: BlockMode| match mode {
+            BlockMode::Hard => 2,
+            BlockMode::Freeze => 1,
+            BlockMode::Soft => 0,
+        };
+
+        let mut found: Option<&BlockRule> = None;
+
+        for compiled in self.rules.iter().filter(|r| Self::rule_matches(r, process)) {
+            let rule = &compiled.rule;
+            if rule.mode == BlockMode::Hard {
+                return Some(rule);
+            }
+            if found.is_none_or(|f| strictness(rule.mode) > strictness(f.mode)) {
+                found = Some(rule);
+            }
+        }
+
+        found
+    }
+}
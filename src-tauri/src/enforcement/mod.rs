@@ -0,0 +1,482 @@
+// Platform-neutral enforcement core
+//
+// A `ProcessSource` knows how to list and signal processes on one platform,
+// `RuleMatcher` decides which rule (if any) applies to a process, and the
+// `Enforcer` ties the two together for each monitor tick.
+
+mod allowlist;
+mod matcher;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "windows")]
+mod windows;
+
+pub use allowlist::ProtectedList;
+pub use matcher::{validate_rule, RuleMatcher};
+
+use crate::db::BlockMode;
+use std::collections::{HashMap, HashSet};
+
+#[cfg(target_os = "linux")]
+pub type PlatformSource = linux::ProcfsSource;
+#[cfg(target_os = "windows")]
+pub type PlatformSource = windows::SysinfoSource;
+
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub exe_path: Option<String>,
+    pub cmdline: Vec<String>,
+    // Kernel name capped at 15 bytes (Linux only); scripts and renamed
+    // binaries keep their own name here while `name` is the exe's
+    pub comm: Option<String>,
+    // Owns a window (Windows) or runs a .desktop entry's program (Linux); only GUI
+    // processes are candidates in allowlist mode
+    pub gui: bool,
+}
+
+pub trait ProcessSource: Send {
+    fn processes(&mut self) -> Vec<ProcessInfo>;
+    fn kill(&mut self, pid: u32) -> bool;
+    fn suspend(&mut self, pid: u32) -> bool;
+    fn resume(&mut self, pid: u32) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Killed,
+    KillFailed,
+    Warned,
+    Frozen,
+    FreezeFailed,
+}
+
+impl Action {
+    // Stored in block_events.action
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Killed => "killed",
+            Action::KillFailed => "kill_failed",
+            Action::Warned => "warned",
+            Action::Frozen => "frozen",
+            Action::FreezeFailed => "freeze_failed",
+        }
+    }
+}
+
+// Shown in place of a rule for processes blocked by allowlist mode
+pub const NOT_ALLOWED: &str = "(not on the allowlist)";
+
+#[derive(Debug, Clone)]
+pub struct Enforcement {
+    pub process: ProcessInfo,
+    // None when allowlist mode blocked a process no rule mentions
+    pub rule_id: Option<String>,
+    pub rule_app_id: String,
+    pub action: Action,
+}
+
+// A process that can't be killed or frozen fails again on every tick; it is
+// reported again only after this long
+const FAILURE_REPORT_SECS: i64 = 10 * 60;
+
+// When each failed (pid, rule, action) was last reported
+#[derive(Default)]
+pub struct FailureTracker {
+    last_reported: HashMap<(u32, Option<String>, Action), i64>,
+}
+
+impl FailureTracker {
+    pub fn new() -> Self {
+        FailureTracker::default()
+    }
+
+    // True if this enforcement should be logged. Successes always are;
+    // failures once per (pid, rule) and window.
+    pub fn should_report(&mut self, enforcement: &Enforcement, now: i64) -> bool {
+        if !matches!(enforcement.action, Action::KillFailed | Action::FreezeFailed) {
+            return true;
+        }
+
+        // Forget old failures so exited pids don't pile up
+        self.last_reported.retain(|_, at| now - *at < FAILURE_REPORT_SECS);
+
+        let key = (enforcement.process.pid, enforcement.rule_id.clone(), enforcement.action);
+        if self.last_reported.contains_key(&key) {
+            return false;
+        }
+        self.last_reported.insert(key, now);
+        true
+    }
+}
+
+pub enum Policy {
+    // Block whatever matches a rule
+    Blocklist(RuleMatcher),
+    // Block every GUI process that matches no rule and isn't protected
+    Allowlist {
+        allowed: RuleMatcher,
+        mode: BlockMode,
+        protected: ProtectedList,
+    },
+}
+
+pub struct Enforcer<S: ProcessSource> {
+    source: S,
+    // Suspended processes stay in the process table; don't act on them twice
+    frozen: HashSet<u32>,
+}
+
+impl<S: ProcessSource> Enforcer<S> {
+    pub fn new(source: S) -> Self {
+        Enforcer {
+            source,
+            frozen: HashSet::new(),
+        }
+    }
+
+    // The processes still suspended, as recorded in the database. Replaces the
+    // previous set, so a pid thawed since (or reused by a new process) is
+    // enforced again.
+    pub fn set_frozen(&mut self, pids: impl IntoIterator<Item = u32>) {
+        self.frozen = pids.into_iter().collect();
+    }
+
+    // One pass over the process table: kill hard-blocked processes, report
+    // soft-blocked ones, and return everything that was acted on
+    pub fn enforce(&mut self, policy: &Policy) -> Vec<Enforcement> {
+        if let Policy::Blocklist(matcher) = policy {
+            if matcher.is_empty() {
+                return Vec::new();
+            }
+        }
+
+        let own_pid = std::process::id();
+        let mut enforcements = Vec::new();
+
+        for process in self.source.processes() {
+            if process.pid == own_pid || self.frozen.contains(&process.pid) {
+                continue;
+            }
+
+            let (rule_id, rule_app_id, mode) = match policy {
+                Policy::Blocklist(matcher) => match matcher.find(&process) {
+                    Some(rule) => (Some(rule.id.clone()), rule.app_id.clone(), rule.mode),
+                    None => continue,
+                },
+                Policy::Allowlist { allowed, mode, protected } => {
+                    if !process.gui || protected.contains(&process) || allowed.find(&process).is_some() {
+                        continue;
+                    }
+                    (None, NOT_ALLOWED.to_string(), *mode)
+                }
+            };
+
+            let action = match mode {
+                BlockMode::Hard => {
+                    if self.source.kill(process.pid) {
+                        Action::Killed
+                    } else {
+                        Action::KillFailed
+                    }
+                }
+                BlockMode::Soft => Action::Warned,
+                BlockMode::Freeze => {
+                    if self.source.suspend(process.pid) {
+                        self.frozen.insert(process.pid);
+                        Action::Frozen
+                    } else {
+                        Action::FreezeFailed
+                    }
+                }
+            };
+
+            enforcements.push(Enforcement {
+                rule_id,
+                rule_app_id,
+                process,
+                action,
+            });
+        }
+
+        enforcements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{BlockRule, MatchKind};
+
+    #[derive(Default)]
+    struct FakeSource {
+        processes: Vec<ProcessInfo>,
+        killed: Vec<u32>,
+        suspended: Vec<u32>,
+    }
+
+    impl ProcessSource for FakeSource {
+        fn processes(&mut self) -> Vec<ProcessInfo> {
+            self.processes
+                .iter()
+                .filter(|p| !self.killed.contains(&p.pid))
+                .cloned()
+                .collect()
+        }
+
+        fn kill(&mut self, pid: u32) -> bool {
+            self.killed.push(pid);
+            true
+        }
+
+        fn suspend(&mut self, pid: u32) -> bool {
+            self.suspended.push(pid);
+            true
+        }
+
+        fn resume(&mut self, pid: u32) -> bool {
+            self.suspended.retain(|p| *p != pid);
+            true
+        }
+    }
+
+    fn process(pid: u32, name: &str, exe_path: &str) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: name.to_string(),
+            exe_path: Some(exe_path.to_string()),
+            cmdline: vec![exe_path.to_string()],
+            comm: None,
+            gui: true,
+        }
+    }
+
+    fn rule(app_id: &str, match_kind: &str, mode: &str) -> BlockRule {
+        BlockRule {
+            id: format!("{}-{}-{}", app_id, match_kind, mode),
+            app_id: app_id.to_string(),
+            match_kind: match_kind.parse().unwrap(),
+            mode: mode.parse().unwrap(),
+            profile_id: None,
+        }
+    }
+
+    fn run(processes: Vec<ProcessInfo>, rules: Vec<BlockRule>) -> (Vec<Enforcement>, FakeSource) {
+        let mut enforcer = Enforcer::new(FakeSource { processes, ..Default::default() });
+        let enforcements = enforcer.enforce(&Policy::Blocklist(RuleMatcher::new(rules)));
+        (enforcements, enforcer.source)
+    }
+
+    #[test]
+    fn every_match_kind_and_mode() {
+        let cases = [
+            ("exe", "Discord.exe"),
+            ("path", r"C:\Apps\Discord"),
+            ("package", "discord"),
+            ("lnk", "Discord"),
+        ];
+
+        for (match_kind, app_id) in cases {
+            for mode in ["hard", "soft"] {
+                let (enforcements, source) = run(
+                    vec![
+                        process(1, "Discord.exe", r"C:\Apps\Discord\Discord.exe"),
+                        process(2, "notepad.exe", r"C:\Windows\notepad.exe"),
+                    ],
+                    vec![rule(app_id, match_kind, mode)],
+                );
+
+                assert_eq!(enforcements.len(), 1, "{} / {}", match_kind, mode);
+                assert_eq!(enforcements[0].process.pid, 1);
+
+                if mode == "hard" {
+                    assert_eq!(enforcements[0].action, Action::Killed);
+                    assert_eq!(source.killed, vec![1]);
+                } else {
+                    assert_eq!(enforcements[0].action, Action::Warned);
+                    assert!(source.killed.is_empty());
+                }
+                assert!(source.suspended.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn exe_rules_match_across_platforms() {
+        let (enforcements, _) = run(
+            vec![process(7, "code", "/usr/share/code/code")],
+            vec![rule("Code.exe", "exe", "hard")],
+        );
+        assert_eq!(enforcements.len(), 1);
+
+        let (enforcements, _) = run(
+            vec![process(7, "vscode-helper", "/usr/bin/vscode-helper")],
+            vec![rule("code", "exe", "hard")],
+        );
+        assert!(enforcements.is_empty());
+    }
+
+    #[test]
+    fn long_exe_rules_match_the_truncated_comm() {
+        // A script run by its interpreter: the exe is python, comm the script
+        let mut script = process(9, "python3.12", "/usr/bin/python3.12");
+        script.comm = Some("focus-breaker-d".to_string());
+
+        let (enforcements, _) = run(vec![script], vec![rule("focus-breaker-daemon", "exe", "hard")]);
+        assert_eq!(enforcements.len(), 1);
+
+        // Names that fit in 15 bytes must match in full
+        let mut short = process(10, "python3.12", "/usr/bin/python3.12");
+        short.comm = Some("steam".to_string());
+        let (enforcements, _) = run(vec![short], vec![rule("stea", "exe", "hard")]);
+        assert!(enforcements.is_empty());
+    }
+
+    #[test]
+    fn path_rules_fall_back_to_argv0() {
+        let mut appimage = process(3, "ld-linux-x86-64.so.2", "/usr/lib/ld-linux-x86-64.so.2");
+        appimage.cmdline = vec!["/home/me/Apps/Obsidian.AppImage".to_string()];
+
+        let (enforcements, _) = run(vec![appimage], vec![rule("/home/me/Apps/Obsidian", "path", "hard")]);
+        assert_eq!(enforcements.len(), 1);
+    }
+
+    #[test]
+    fn regex_rules_select_their_target() {
+        let mut browser = process(6, "firefox", "/usr/lib/firefox/firefox");
+        browser.cmdline = vec!["/usr/lib/firefox/firefox".to_string(), "--kiosk".to_string()];
+
+        for (app_id, expected) in [
+            ("^fire(fox|dragon)$", true),
+            ("name:^FIREFOX$", true),
+            ("name:/usr/lib", false),
+            ("path:^/usr/lib/.+/firefox$", true),
+            ("cmdline:--kiosk", true),
+            ("path:--kiosk", false),
+        ] {
+            let (enforcements, _) = run(vec![browser.clone()], vec![rule(app_id, "regex", "soft")]);
+            assert_eq!(enforcements.len(), expected as usize, "{}", app_id);
+        }
+    }
+
+    #[test]
+    fn invalid_regex_is_rejected_and_skipped() {
+        assert!(validate_rule("name:(unclosed", MatchKind::Regex).is_err());
+        assert!(validate_rule("path:", MatchKind::Regex).is_err());
+        assert!(validate_rule("(unclosed", MatchKind::Exe).is_ok());
+
+        let (enforcements, _) = run(
+            vec![process(8, "steam", "/usr/bin/steam")],
+            vec![rule("(unclosed", "regex", "hard"), rule("steam", "exe", "soft")],
+        );
+        assert_eq!(enforcements.len(), 1);
+        assert_eq!(enforcements[0].action, Action::Warned);
+    }
+
+    #[test]
+    fn hard_rule_wins_over_soft() {
+        let (enforcements, source) = run(
+            vec![process(4, "steam", "/usr/bin/steam")],
+            vec![rule("steam", "exe", "soft"), rule("/usr/bin/steam", "path", "hard")],
+        );
+
+        assert_eq!(enforcements.len(), 1);
+        assert_eq!(enforcements[0].action, Action::Killed);
+        assert_eq!(source.killed, vec![4]);
+    }
+
+    #[test]
+    fn allowlist_blocks_unlisted_gui_processes() {
+        let mut shell = process(12, "bash", "/usr/bin/bash");
+        shell.gui = false;
+        let processes = vec![
+            process(10, "code", "/usr/share/code/code"),
+            process(11, "steam", "/usr/bin/steam"),
+            shell,
+            process(13, "gnome-shell", "/usr/bin/gnome-shell"),
+            process(14, "explorer.exe", r"C:\Windows\explorer.exe"),
+            process(15, "kitty", "/usr/bin/kitty"),
+        ];
+
+        let run_allowlist = |terminals: bool| {
+            let mut enforcer = Enforcer::new(FakeSource { processes: processes.clone(), ..Default::default() });
+            let enforcements = enforcer.enforce(&Policy::Allowlist {
+                allowed: RuleMatcher::new(vec![rule("code", "exe", "hard")]),
+                mode: BlockMode::Hard,
+                protected: ProtectedList::new(terminals),
+            });
+            (enforcements, enforcer.source)
+        };
+
+        // Only the shell for this platform is protected
+        let shell_pid = if cfg!(target_os = "windows") { 13 } else { 14 };
+
+        let (enforcements, source) = run_allowlist(false);
+        assert!(enforcements.iter().all(|e| e.rule_id.is_none() && e.action == Action::Killed));
+        assert_eq!(source.killed, vec![11, shell_pid, 15]);
+
+        let (_, source) = run_allowlist(true);
+        assert_eq!(source.killed, vec![11, shell_pid]);
+    }
+
+    #[test]
+    fn freeze_suspends_once_and_ranks_between_hard_and_soft() {
+        let mut enforcer = Enforcer::new(FakeSource {
+            processes: vec![process(20, "code", "/usr/bin/code"), process(21, "steam", "/usr/bin/steam")],
+            ..Default::default()
+        });
+        let policy = Policy::Blocklist(RuleMatcher::new(vec![
+            rule("code", "exe", "soft"),
+            rule("code", "exe", "freeze"),
+            rule("steam", "exe", "freeze"),
+            rule("steam", "exe", "hard"),
+        ]));
+
+        let enforcements = enforcer.enforce(&policy);
+        let actions: Vec<(u32, Action)> = enforcements.iter().map(|e| (e.process.pid, e.action)).collect();
+        assert_eq!(actions, vec![(20, Action::Frozen), (21, Action::Killed)]);
+        assert_eq!(enforcer.source.suspended, vec![20]);
+
+        // Still in the process table while stopped, but not frozen again
+        assert!(enforcer.enforce(&policy).is_empty());
+        assert_eq!(enforcer.source.suspended, vec![20]);
+
+        // Once thawed it is no longer in the recorded set, and gets frozen again
+        enforcer.set_frozen([]);
+        let actions: Vec<(u32, Action)> = enforcer.enforce(&policy).iter().map(|e| (e.process.pid, e.action)).collect();
+        assert_eq!(actions, vec![(20, Action::Frozen)]);
+        assert_eq!(enforcer.source.suspended, vec![20, 20]);
+    }
+
+    #[test]
+    fn repeated_failures_are_reported_once_per_window() {
+        let failed = |pid: u32, rule_id: &str, action: Action| Enforcement {
+            process: process(pid, "steam", "/usr/bin/steam"),
+            rule_id: Some(rule_id.to_string()),
+            rule_app_id: "steam".to_string(),
+            action,
+        };
+        let mut tracker = FailureTracker::new();
+
+        assert!(tracker.should_report(&failed(30, "r1", Action::KillFailed), 0));
+        assert!(!tracker.should_report(&failed(30, "r1", Action::KillFailed), 2));
+        assert!(tracker.should_report(&failed(31, "r1", Action::KillFailed), 2));
+        assert!(tracker.should_report(&failed(30, "r2", Action::KillFailed), 2));
+        assert!(tracker.should_report(&failed(30, "r1", Action::FreezeFailed), 2));
+        assert!(tracker.should_report(&failed(30, "r1", Action::KillFailed), FAILURE_REPORT_SECS));
+
+        // Successful actions are never held back
+        assert!(tracker.should_report(&failed(30, "r1", Action::Killed), 4));
+        assert!(tracker.should_report(&failed(30, "r1", Action::Killed), 6));
+    }
+
+    #[test]
+    fn rule_values_parse_strictly() {
+        assert_eq!("hard".parse::<BlockMode>(), Ok(BlockMode::Hard));
+        assert!("Hard".parse::<BlockMode>().is_err());
+        assert!(" soft".parse::<BlockMode>().is_err());
+        assert_eq!("regex".parse::<MatchKind>(), Ok(MatchKind::Regex));
+        assert!("EXE".parse::<MatchKind>().is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use super::{ProcessInfo, ProcessSource};
+use std::collections::HashSet;
+use sysinfo::{Pid, ProcessesToUpdate, Signal, System};
+use windows_sys::Win32::Foundation::{BOOL, HWND, LPARAM};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetWindow, GetWindowThreadProcessId, IsWindowVisible, GW_OWNER,
+};
+
+// sysinfo-backed source; the `System` is kept between polls so refreshes
+// only have to diff the process table
+pub struct SysinfoSource {
+    sys: System,
+}
+
+impl SysinfoSource {
+    pub fn new() -> Self {
+        SysinfoSource { sys: System::new() }
+    }
+}
+
+unsafe extern "system" fn collect_window_pid(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    // Visible, unowned top-level windows are the ones on the taskbar
+    if IsWindowVisible(hwnd) != 0 && GetWindow(hwnd, GW_OWNER).is_null() {
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        let pids = &mut *(lparam as *mut HashSet<u32>);
+        pids.insert(pid);
+    }
+    1
+}
+
+// Processes that own at least one top-level window
+fn windowed_pids() -> HashSet<u32> {
+    let mut pids = HashSet::new();
+    unsafe {
+        EnumWindows(Some(collect_window_pid), &mut pids as *mut HashSet<u32> as LPARAM);
+    }
+    pids
+}
+
+impl ProcessSource for SysinfoSource {
+    fn processes(&mut self) -> Vec<ProcessInfo> {
+        self.sys.refresh_processes(ProcessesToUpdate::All);
+        let windowed = windowed_pids();
+
+        self.sys
+            .processes()
+            .iter()
+            .map(|(pid, process)| ProcessInfo {
+                pid: pid.as_u32(),
+                name: process.name().to_string_lossy().to_string(),
+                exe_path: process.exe().and_then(|p| p.to_str().map(String::from)),
+                cmdline: process.cmd().iter().map(|arg| arg.to_string_lossy().to_string()).collect(),
+                comm: None,
+                gui: windowed.contains(&pid.as_u32()),
+            })
+            .collect()
+    }
+
+    fn kill(&mut self, pid: u32) -> bool {
+        self.sys
+            .process(Pid::from_u32(pid))
+            .and_then(|p| p.kill_with(Signal::Kill))
+            .unwrap_or(false)
+    }
+
+    fn suspend(&mut self, pid: u32) -> bool {
+        // Signal::Stop is not supported on Windows; sysinfo reports None
+        self.sys
+            .process(Pid::from_u32(pid))
+            .and_then(|p| p.kill_with(Signal::Stop))
+            .unwrap_or(false)
+    }
+
+    fn resume(&mut self, pid: u32) -> bool {
+        self.sys
+            .process(Pid::from_u32(pid))
+            .and_then(|p| p.kill_with(Signal::Continue))
+            .unwrap_or(false)
+    }
+}
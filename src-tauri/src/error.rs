@@ -0,0 +1,223 @@
+// Error type shared by every Tauri command
+//
+// Serializes as `{ code, message, details }`: `code` is stable for the
+// frontend to branch on, `message` is meant for display, and `details` carries
+// structured context (the rejected field, the locked action, ...) when there
+// is any.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::{json, Value};
+use std::fmt;
+use std::sync::PoisonError;
+
+#[derive(Debug)]
+pub enum FocusLockError {
+    NotFound { entity: String, id: Option<String> },
+    Validation { field: String, value: String, message: String },
+    // The request is well-formed but not allowed from the current state
+    InvalidState(String),
+    StrictModeLocked { action: String },
+    // An admin passphrase is set and no valid unlock token was given
+    PassphraseRequired { action: String },
+    RateLimited { retry_after_secs: i64 },
+    Database(String),
+    Platform(String),
+    // Only built on platforms without process or icon support
+    #[cfg_attr(any(target_os = "windows", target_os = "linux"), allow(dead_code))]
+    Unsupported(String),
+    Internal(String),
+}
+
+impl FocusLockError {
+    pub fn not_found(entity: &str, id: &str) -> Self {
+        FocusLockError::NotFound {
+            entity: entity.to_string(),
+            id: Some(id.to_string()),
+        }
+    }
+
+    pub fn validation(field: &str, value: &str, message: impl Into<String>) -> Self {
+        FocusLockError::Validation {
+            field: field.to_string(),
+            value: value.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            FocusLockError::NotFound { .. } => "notFound",
+            FocusLockError::Validation { .. } => "validation",
+            FocusLockError::InvalidState(_) => "invalidState",
+            FocusLockError::StrictModeLocked { .. } => "strictModeLocked",
+            FocusLockError::PassphraseRequired { .. } => "passphraseRequired",
+            FocusLockError::RateLimited { .. } => "rateLimited",
+            FocusLockError::Database(_) => "database",
+            FocusLockError::Platform(_) => "platform",
+            FocusLockError::Unsupported(_) => "unsupported",
+            FocusLockError::Internal(_) => "internal",
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        match self {
+            FocusLockError::NotFound { entity, id } => Some(json!({ "entity": entity, "id": id })),
+            FocusLockError::Validation { field, value, .. } => Some(json!({ "field": field, "value": value })),
+            FocusLockError::StrictModeLocked { action } | FocusLockError::PassphraseRequired { action } => {
+                Some(json!({ "action": action }))
+            }
+            FocusLockError::RateLimited { retry_after_secs } => Some(json!({ "retryAfterSecs": retry_after_secs })),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FocusLockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FocusLockError::NotFound { entity, id: Some(id) } => write!(f, "No {} with id {}", entity, id),
+            FocusLockError::NotFound { entity, id: None } => write!(f, "{} not found", entity),
+            FocusLockError::Validation { message, .. } => write!(f, "{}", message),
+            FocusLockError::StrictModeLocked { action } => {
+                write!(f, "Strict mode is on: can't {} until the current session ends", action)
+            }
+            FocusLockError::PassphraseRequired { action } => {
+                write!(f, "Enter the admin passphrase to {}", action)
+            }
+            FocusLockError::RateLimited { retry_after_secs } => {
+                write!(f, "Too many wrong attempts, try again in {} seconds", retry_after_secs)
+            }
+            FocusLockError::InvalidState(message)
+            | FocusLockError::Database(message)
+            | FocusLockError::Platform(message)
+            | FocusLockError::Unsupported(message)
+            | FocusLockError::Internal(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for FocusLockError {}
+
+impl Serialize for FocusLockError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("FocusLockError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<rusqlite::Error> for FocusLockError {
+    fn from(err: rusqlite::Error) -> Self {
+        match err {
+            // Callers that know what they were looking up use `not_found`
+            rusqlite::Error::QueryReturnedNoRows => FocusLockError::NotFound {
+                entity: "Record".to_string(),
+                id: None,
+            },
+            err => FocusLockError::Database(err.to_string()),
+        }
+    }
+}
+
+impl<T> From<PoisonError<T>> for FocusLockError {
+    fn from(_: PoisonError<T>) -> Self {
+        FocusLockError::Internal("Database lock was poisoned by a panic".to_string())
+    }
+}
+
+impl From<std::io::Error> for FocusLockError {
+    fn from(err: std::io::Error) -> Self {
+        FocusLockError::Platform(err.to_string())
+    }
+}
+
+// Only returned when a spawned task fails to join
+impl From<tauri::Error> for FocusLockError {
+    fn from(err: tauri::Error) -> Self {
+        FocusLockError::Internal(format!("Task error: {}", err))
+    }
+}
+
+pub type CommandResult<T> = Result<T, FocusLockError>;
+
+// Turns "no rows" into a NotFound naming what was looked up
+pub trait OrNotFound<T> {
+    fn or_not_found(self, entity: &str, id: &str) -> CommandResult<T>;
+}
+
+impl<T> OrNotFound<T> for rusqlite::Result<T> {
+    fn or_not_found(self, entity: &str, id: &str) -> CommandResult<T> {
+        self.map_err(|err| match err {
+            rusqlite::Error::QueryReturnedNoRows => FocusLockError::not_found(entity, id),
+            err => err.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_code_message_and_details() {
+        let err = FocusLockError::validation("mode", "Hard", "'Hard' is not one of: hard, soft");
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({
+                "code": "validation",
+                "message": "'Hard' is not one of: hard, soft",
+                "details": { "field": "mode", "value": "Hard" },
+            })
+        );
+
+        let err: FocusLockError = rusqlite::Result::<()>::Err(rusqlite::Error::QueryReturnedNoRows)
+            .or_not_found("session", "abc")
+            .unwrap_err();
+        assert_eq!(err.code(), "notFound");
+        assert_eq!(err.to_string(), "No session with id abc");
+
+        let err = FocusLockError::Unsupported("Not on this platform".to_string());
+        assert_eq!(serde_json::to_value(&err).unwrap()["details"], Value::Null);
+    }
+
+    #[test]
+    fn every_variant_has_a_stable_code_and_details() {
+        let locked = FocusLockError::StrictModeLocked { action: "delete a block rule".to_string() };
+        assert_eq!(
+            serde_json::to_value(&locked).unwrap(),
+            json!({
+                "code": "strictModeLocked",
+                "message": "Strict mode is on: can't delete a block rule until the current session ends",
+                "details": { "action": "delete a block rule" },
+            })
+        );
+
+        let passphrase = FocusLockError::PassphraseRequired { action: "turn off strict mode".to_string() };
+        assert_eq!(passphrase.code(), "passphraseRequired");
+        assert_eq!(serde_json::to_value(&passphrase).unwrap()["details"], json!({ "action": "turn off strict mode" }));
+
+        let limited = FocusLockError::RateLimited { retry_after_secs: 30 };
+        assert_eq!(limited.code(), "rateLimited");
+        assert_eq!(serde_json::to_value(&limited).unwrap()["details"], json!({ "retryAfterSecs": 30 }));
+
+        assert_eq!(FocusLockError::InvalidState("Too late".to_string()).code(), "invalidState");
+
+        // Conversions from the errors commands bubble up with `?`
+        let err: FocusLockError = rusqlite::Error::QueryReturnedNoRows.into();
+        assert_eq!((err.code(), err.to_string()), ("notFound", "Record not found".to_string()));
+        let err: FocusLockError = rusqlite::Error::InvalidQuery.into();
+        assert_eq!(err.code(), "database");
+        let err: FocusLockError = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied").into();
+        assert_eq!((err.code(), err.to_string()), ("platform", "denied".to_string()));
+
+        let lock = std::sync::Mutex::new(());
+        let _ = std::panic::catch_unwind(|| {
+            let _guard = lock.lock().unwrap();
+            panic!("poison the lock");
+        });
+        let err: FocusLockError = lock.lock().unwrap_err().into();
+        assert_eq!(err.code(), "internal");
+    }
+}
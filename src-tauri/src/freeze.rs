@@ -0,0 +1,121 @@
+// Processes suspended by `freeze` rules
+//
+// Frozen pids are written to `frozen_processes` as soon as they are stopped,
+// so they can be resumed when their session stops enforcing even if
+// FocusLock crashed in between. The session ticker thaws everything whose
+// session ended, is paused or is on a pomodoro break, and startup recovery
+// does the same; the monitor freezes them again once work resumes.
+
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+
+use crate::db::{self, SessionStatus};
+use crate::pomodoro;
+
+pub fn record_frozen(conn: &Connection, session_id: &str, pid: u32, process_name: &str, now: i64) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO frozen_processes (pid, session_id, process_name, frozen_utc) VALUES (?1, ?2, ?3, ?4)",
+        (pid, session_id, process_name, now),
+    )?;
+    Ok(())
+}
+
+// Every pid still held by an enforcing session
+pub fn frozen_pids(conn: &Connection) -> SqliteResult<Vec<u32>> {
+    let mut stmt = conn.prepare("SELECT pid FROM frozen_processes")?;
+    let pids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    Ok(pids)
+}
+
+// Resumes processes whose session no longer enforces (ended, vanished,
+// paused or on a break) and forgets them. Returns how many rows were cleared.
+pub fn thaw_unenforced(conn: &Connection, now: i64) -> SqliteResult<usize> {
+    let mut stmt = conn.prepare("SELECT pid, process_name, session_id FROM frozen_processes")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    let mut finished = Vec::new();
+    for (pid, name, session_id) in rows {
+        let enforcing = match db::find_session(conn, &session_id).optional()? {
+            Some(session) => session.status == SessionStatus::Running && pomodoro::enforcing_with(conn, &session, now),
+            None => false,
+        };
+        if !enforcing {
+            finished.push((pid, name));
+        }
+    }
+
+    if finished.is_empty() {
+        return Ok(0);
+    }
+
+    resume(&finished);
+
+    for (pid, _) in &finished {
+        conn.execute("DELETE FROM frozen_processes WHERE pid = ?1", [pid])?;
+    }
+    Ok(finished.len())
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn resume(frozen: &[(u32, String)]) {
+    use crate::enforcement::{PlatformSource, ProcessSource};
+    use std::collections::HashMap;
+
+    let mut source = PlatformSource::new();
+    let running: HashMap<u32, String> = source.processes().into_iter().map(|p| (p.pid, p.name)).collect();
+
+    for (pid, name) in frozen {
+        // Gone, or the pid was recycled by some other program
+        if running.get(pid) != Some(name) {
+            continue;
+        }
+        if source.resume(*pid) {
+            println!("[Monitor] Resumed {} (pid {})", name, pid);
+        } else {
+            println!("[Monitor] Failed to resume {} (pid {})", name, pid);
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn resume(_frozen: &[(u32, String)]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_rows_of_enforcing_sessions_stay_frozen() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+        conn.execute_batch(
+            "INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs) VALUES ('live', 0, 1000, 'running', 1000);
+             INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs, remaining_secs)
+             VALUES ('paused', 0, 1000, 'paused', 1000, 500);
+             INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs) VALUES ('done', 0, 100, 'canceled', 100);
+             INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs) VALUES ('pomo', 0, 1000, 'running', 1000);
+             INSERT INTO pomodoro_sessions (session_id, work_secs, short_break_secs, long_break_secs, work_intervals, long_break_every)
+             VALUES ('pomo', 300, 60, 120, 3, 4);",
+        )
+        .unwrap();
+
+        // Pids far above pid_max so nothing real is signalled
+        record_frozen(&conn, "live", 90_000_001, "code", 10).unwrap();
+        record_frozen(&conn, "paused", 90_000_002, "steam", 10).unwrap();
+        record_frozen(&conn, "done", 90_000_003, "slack", 10).unwrap();
+        record_frozen(&conn, "deleted", 90_000_004, "discord", 10).unwrap();
+        record_frozen(&conn, "pomo", 90_000_005, "spotify", 10).unwrap();
+
+        // 100s in, the pomodoro session is still on its first work interval
+        assert_eq!(thaw_unenforced(&conn, 100).unwrap(), 3);
+        assert_eq!(frozen_pids(&conn).unwrap(), vec![90_000_001, 90_000_005]);
+
+        // and on a break at 320s
+        assert_eq!(thaw_unenforced(&conn, 320).unwrap(), 1);
+        assert_eq!(frozen_pids(&conn).unwrap(), vec![90_000_001]);
+        assert_eq!(thaw_unenforced(&conn, 320).unwrap(), 0);
+    }
+}
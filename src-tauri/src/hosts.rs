@@ -0,0 +1,306 @@
+// Website blocking through the system hosts file
+//
+// While a session is enforcing, the host names of its `domain` rules are
+// pointed at 0.0.0.0 in a marked section at the end of the hosts file. A
+// background loop keeps that section in line with the current session: it is
+// rewritten only when its contents change and removed as soon as no session
+// needs it, which also cleans up after a crash on the next start. The file is
+// replaced atomically, and the last copy without a FocusLock section is kept
+// as `hosts.backup` in the app data directory.
+//
+// `hostsFilePath` overrides the platform's hosts file. Writing the real one
+// needs elevated rights; failures are logged and retried.
+
+use rusqlite::{Connection, Result as SqliteResult};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::{self, DbState};
+use crate::domains;
+use crate::error::CommandResult;
+use crate::session::now_utc;
+
+pub const HOSTS_PATH_KEY: &str = "hostsFilePath";
+
+const BEGIN_MARKER: &str = "# >>> FocusLock blocked sites (managed by FocusLock, do not edit) >>>";
+const END_MARKER: &str = "# <<< FocusLock blocked sites <<<";
+const BLOCKED_ADDRESS: &str = "0.0.0.0";
+const SYNC_INTERVAL_SECS: u64 = 2;
+
+#[cfg(target_os = "windows")]
+const DEFAULT_HOSTS_PATH: &str = r"C:\Windows\System32\drivers\etc\hosts";
+#[cfg(not(target_os = "windows"))]
+const DEFAULT_HOSTS_PATH: &str = "/etc/hosts";
+
+// `content` without any FocusLock section. A section missing its end marker
+// was cut short, and runs to the end of the file.
+pub fn strip_section(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut inside = false;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if !inside && trimmed == BEGIN_MARKER {
+            inside = true;
+        } else if inside && trimmed == END_MARKER {
+            inside = false;
+        } else if !inside {
+            out.push_str(line);
+        }
+    }
+    out
+}
+
+// `content` with its FocusLock section replaced by one blocking `hosts`, or
+// removed when there's nothing to block
+pub fn render(content: &str, hosts: &[String]) -> String {
+    let mut out = strip_section(content);
+    if hosts.is_empty() {
+        return out;
+    }
+
+    // Keep Windows line endings where the file already uses them
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push_str(newline);
+    }
+
+    out.push_str(BEGIN_MARKER);
+    out.push_str(newline);
+    for host in hosts {
+        out.push_str(&format!("{} {}{}", BLOCKED_ADDRESS, host, newline));
+    }
+    out.push_str(END_MARKER);
+    out.push_str(newline);
+    out
+}
+
+pub struct HostsFile {
+    path: PathBuf,
+    backup_path: PathBuf,
+}
+
+impl HostsFile {
+    pub fn new(path: impl Into<PathBuf>, backup_path: impl Into<PathBuf>) -> Self {
+        HostsFile {
+            path: path.into(),
+            backup_path: backup_path.into(),
+        }
+    }
+
+    // Makes the FocusLock section block exactly `hosts`. Returns whether the
+    // file had to be rewritten.
+    pub fn apply(&self, hosts: &[String]) -> io::Result<bool> {
+        let current = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && hosts.is_empty() => return Ok(false),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+
+        let updated = render(&current, hosts);
+        if updated == current {
+            return Ok(false);
+        }
+
+        if !current.lines().any(|line| line.trim_end() == BEGIN_MARKER) {
+            fs::write(&self.backup_path, &current)?;
+        }
+        write_atomically(&self.path, &updated)?;
+        Ok(true)
+    }
+}
+
+// Written next to the target and renamed over it, so a crash never leaves a
+// half-written hosts file
+fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".focuslock-tmp");
+    let tmp = path.with_file_name(tmp_name);
+
+    let result = fs::write(&tmp, content)
+        .and_then(|_| match fs::metadata(path) {
+            Ok(meta) => fs::set_permissions(&tmp, meta.permissions()),
+            Err(_) => Ok(()),
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+// Host names the hosts file should block right now
+pub fn blocked_hosts(conn: &Connection, now: i64) -> SqliteResult<Vec<String>> {
+    let enforced = domains::enforced_domains(conn, now)?;
+    let hosts: BTreeSet<String> = enforced.rules.iter().flat_map(|(_, rule)| rule.host_names()).collect();
+    Ok(hosts.into_iter().collect())
+}
+
+fn configured_path(conn: &Connection) -> SqliteResult<PathBuf> {
+    Ok(match db::get_setting(conn, HOSTS_PATH_KEY)? {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => PathBuf::from(DEFAULT_HOSTS_PATH),
+    })
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HostsStatus {
+    // The hosts file blocked sites are written to
+    pub path: String,
+    // Host names in its FocusLock section after the last successful sync
+    pub blocked_hosts: Vec<String>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct SyncState {
+    // The file last written to, so a path change cleans up the old one
+    applied_path: Option<PathBuf>,
+    applied_hosts: Vec<String>,
+    last_error: Option<String>,
+}
+
+pub struct HostsBlocker {
+    backup_path: PathBuf,
+    state: Mutex<SyncState>,
+}
+
+impl HostsBlocker {
+    pub fn new(app_data_dir: &Path) -> Self {
+        HostsBlocker {
+            backup_path: app_data_dir.join("hosts.backup"),
+            state: Mutex::new(SyncState::default()),
+        }
+    }
+
+    pub fn sync(&self, db: &DbState) {
+        let (hosts, path) = {
+            let Ok(conn) = db.conn.lock() else {
+                return;
+            };
+            match (blocked_hosts(&conn, now_utc()), configured_path(&conn)) {
+                (Ok(hosts), Ok(path)) => (hosts, path),
+                _ => return,
+            }
+        };
+
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        let mut result = Ok(false);
+        if let Some(old) = state.applied_path.clone().filter(|old| *old != path) {
+            result = HostsFile::new(&old, &self.backup_path).apply(&[]);
+            if result.is_ok() {
+                state.applied_path = None;
+                state.applied_hosts.clear();
+            }
+        }
+
+        let result = result.and_then(|_| HostsFile::new(&path, &self.backup_path).apply(&hosts));
+        match result {
+            Ok(changed) => {
+                if changed && hosts.is_empty() {
+                    println!("[Hosts] Removed blocked sites from {}", path.display());
+                } else if changed {
+                    println!("[Hosts] Blocking {} host names in {}", hosts.len(), path.display());
+                }
+                state.applied_path = Some(path);
+                state.applied_hosts = hosts;
+                state.last_error = None;
+            }
+            Err(e) => {
+                // Retried every sync; only log when the failure changes
+                let message = format!("Failed to update {}: {}", path.display(), e);
+                if state.last_error.as_deref() != Some(message.as_str()) {
+                    println!("[Hosts] {}", message);
+                    state.last_error = Some(message);
+                }
+            }
+        }
+    }
+}
+
+// Tauri Commands - Hosts file
+#[tauri::command]
+pub fn get_hosts_status(db: State<DbState>, blocker: State<HostsBlocker>) -> CommandResult<HostsStatus> {
+    let path = configured_path(&*db.conn.lock()?)?;
+    let state = blocker.state.lock()?;
+
+    Ok(HostsStatus {
+        path: path.display().to_string(),
+        blocked_hosts: state.applied_hosts.clone(),
+        last_error: state.last_error.clone(),
+    })
+}
+
+pub fn spawn_sync(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let db: State<DbState> = app.state();
+            let blocker: State<HostsBlocker> = app.state();
+            blocker.sync(&db);
+
+            tokio::time::sleep(Duration::from_secs(SYNC_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn section_is_added_replaced_and_removed() {
+        let original = "127.0.0.1 localhost\n::1 localhost";
+        let hosts = vec!["reddit.com".to_string(), "www.reddit.com".to_string()];
+
+        let blocked = render(original, &hosts);
+        assert!(blocked.starts_with("127.0.0.1 localhost\n::1 localhost\n# >>> FocusLock"));
+        assert!(blocked.contains("\n0.0.0.0 www.reddit.com\n"));
+        assert_eq!(render(&blocked, &hosts), blocked);
+
+        let fewer = render(&blocked, &hosts[..1]);
+        assert!(!fewer.contains("www.reddit.com"));
+        assert_eq!(render(&fewer, &[]), "127.0.0.1 localhost\n::1 localhost\n");
+
+        // Cut short by a crash mid-write of an older version
+        assert_eq!(strip_section(&format!("a\n{}\n0.0.0.0 x.com\n", BEGIN_MARKER)), "a\n");
+
+        let windows = render("127.0.0.1 localhost\r\n", &hosts);
+        assert!(windows.ends_with("0.0.0.0 www.reddit.com\r\n# <<< FocusLock blocked sites <<<\r\n"));
+    }
+
+    #[test]
+    fn apply_rewrites_only_on_change_and_backs_up_the_original() {
+        let dir = std::env::temp_dir().join(format!("focuslock-hosts-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+        let backup = dir.join("hosts.backup");
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+
+        let file = HostsFile::new(&path, &backup);
+        let hosts = vec!["example.com".to_string()];
+
+        assert!(file.apply(&hosts).unwrap());
+        assert!(!file.apply(&hosts).unwrap());
+        assert!(fs::read_to_string(&path).unwrap().contains("0.0.0.0 example.com"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "127.0.0.1 localhost\n");
+
+        assert!(file.apply(&[]).unwrap());
+        assert!(!file.apply(&[]).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "127.0.0.1 localhost\n");
+        assert!(!dir.join("hosts.focuslock-tmp").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
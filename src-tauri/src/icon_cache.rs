@@ -0,0 +1,164 @@
+// On-disk cache of rendered app icons
+//
+// Entries live in <app data>/icon-cache and are named
+// `<hash of source path + size>-<hash of mtime + length>.icon`, so editing or
+// replacing the source file changes the name and the next lookup re-renders
+// it; the stale sibling is removed at that point.
+
+use image::{ImageEncoder, RgbaImage};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::error::{CommandResult, FocusLockError};
+
+pub const DEFAULT_ICON_SIZE: u32 = 32;
+const MIN_ICON_SIZE: u32 = 8;
+const MAX_ICON_SIZE: u32 = 512;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+pub struct IconCache {
+    dir: PathBuf,
+}
+
+pub fn icon_size(size: Option<u32>) -> CommandResult<u32> {
+    let size = size.unwrap_or(DEFAULT_ICON_SIZE);
+    if !(MIN_ICON_SIZE..=MAX_ICON_SIZE).contains(&size) {
+        return Err(FocusLockError::validation(
+            "size",
+            &size.to_string(),
+            format!("Icon size must be between {} and {}", MIN_ICON_SIZE, MAX_ICON_SIZE),
+        ));
+    }
+    Ok(size)
+}
+
+fn short_hash(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        // Length-prefix each part so ("ab", "c") and ("a", "bc") differ
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    format!("{:x}", hasher.finalize())[..32].to_string()
+}
+
+pub fn encode_png(rgba: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut png_data = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png_data)
+        .write_image(rgba, rgba.width(), rgba.height(), image::ExtendedColorType::Rgba8)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    Ok(png_data)
+}
+
+// Icons are PNG except for raw .ico data we couldn't decode on Windows
+pub fn data_url(data: &[u8]) -> String {
+    let mime = if data.starts_with(PNG_SIGNATURE) { "image/png" } else { "image/x-icon" };
+    let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, data);
+    format!("data:{};base64,{}", mime, encoded)
+}
+
+impl IconCache {
+    pub fn new(dir: PathBuf) -> std::io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(IconCache { dir })
+    }
+
+    fn source_key(source: &Path, size: u32) -> String {
+        short_hash(&[source.to_string_lossy().as_bytes(), &size.to_le_bytes()])
+    }
+
+    fn version_key(source: &Path) -> Option<String> {
+        let meta = fs::metadata(source).ok()?;
+        let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+        Some(short_hash(&[&mtime.to_le_bytes(), &meta.len().to_le_bytes()]))
+    }
+
+    fn remove_stale(&self, source_key: &str, keep: &Path) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let stale = entry.file_name().to_string_lossy().starts_with(&format!("{}-", source_key));
+            if stale && path != keep {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    // Returns the cached bytes for `source` at `size`, rendering and storing
+    // them first if the source is new or has changed
+    pub fn get_or_render(
+        &self,
+        source: &Path,
+        size: u32,
+        render: impl FnOnce() -> Result<Vec<u8>, String>,
+    ) -> Result<Vec<u8>, String> {
+        let Some(version_key) = Self::version_key(source) else {
+            // Nothing to key on; render without caching
+            return render();
+        };
+
+        let source_key = Self::source_key(source, size);
+        let entry = self.dir.join(format!("{}-{}.icon", source_key, version_key));
+
+        if let Ok(data) = fs::read(&entry) {
+            return Ok(data);
+        }
+
+        let data = render()?;
+
+        // Write then rename so a concurrent reader never sees half a file
+        let tmp = entry.with_extension(format!("tmp{}", std::process::id()));
+        if fs::write(&tmp, &data).and_then(|_| fs::rename(&tmp, &entry)).is_ok() {
+            self.remove_stale(&source_key, &entry);
+        } else {
+            let _ = fs::remove_file(&tmp);
+        }
+
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn entries_are_reused_until_the_source_changes() {
+        let root = std::env::temp_dir().join(format!("focuslock-icon-cache-{}", std::process::id()));
+        let source = root.join("app.png");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(&source, "v1").unwrap();
+
+        let cache = IconCache::new(root.join("cache")).unwrap();
+        let renders = Cell::new(0);
+        let render = || {
+            renders.set(renders.get() + 1);
+            Ok(fs::read(&source).unwrap())
+        };
+
+        assert_eq!(cache.get_or_render(&source, 32, render).unwrap(), b"v1");
+        assert_eq!(cache.get_or_render(&source, 32, render).unwrap(), b"v1");
+        assert_eq!(renders.get(), 1);
+
+        // Other sizes are separate entries
+        cache.get_or_render(&source, 64, render).unwrap();
+        assert_eq!(renders.get(), 2);
+
+        // A longer file changes the version key even within mtime granularity
+        fs::write(&source, "v2-changed").unwrap();
+        assert_eq!(cache.get_or_render(&source, 32, render).unwrap(), b"v2-changed");
+        assert_eq!(renders.get(), 3);
+        assert_eq!(fs::read_dir(root.join("cache")).unwrap().count(), 2);
+
+        assert!(icon_size(None).is_ok());
+        assert!(icon_size(Some(4)).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,414 @@
+// Freedesktop icon theme lookup and rasterization
+//
+// Implements the lookup from the Icon Theme Specification: walk the user's
+// current theme, then everything it inherits from, then hicolor, picking the
+// directory whose size matches best; fall back to unthemed icons in the base
+// dirs (including /usr/share/pixmaps). SVGs are rendered at the requested
+// size instead of being handed to `image`, which can't decode them.
+
+use image::imageops::FilterType;
+use image::RgbaImage;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+use crate::desktop_entries::{data_dirs, home_dir};
+
+const FALLBACK_THEME: &str = "hicolor";
+
+// xpm is in the spec, but nothing here can decode it
+const EXTENSIONS: [&str; 2] = ["png", "svg"];
+
+type IniGroups = HashMap<String, HashMap<String, String>>;
+
+fn parse_ini(text: &str) -> IniGroups {
+    let mut groups = IniGroups::new();
+    let mut current = None;
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = Some(name.to_string());
+            groups.entry(name.to_string()).or_default();
+        } else if let (Some(group), Some((key, value))) = (&current, line.split_once('=')) {
+            groups
+                .entry(group.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+
+    groups
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DirKind {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+#[derive(Debug, Clone)]
+struct ThemeDir {
+    subdir: String,
+    size: u32,
+    scale: u32,
+    kind: DirKind,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+}
+
+impl ThemeDir {
+    fn from_group(subdir: &str, group: &HashMap<String, String>) -> Option<ThemeDir> {
+        let num = |key: &str| group.get(key).and_then(|v| v.parse::<u32>().ok());
+        let size = num("Size")?;
+
+        Some(ThemeDir {
+            subdir: subdir.to_string(),
+            size,
+            scale: num("Scale").unwrap_or(1).max(1),
+            kind: match group.get("Type").map(String::as_str) {
+                Some("Fixed") => DirKind::Fixed,
+                Some("Scalable") => DirKind::Scalable,
+                _ => DirKind::Threshold,
+            },
+            min_size: num("MinSize").unwrap_or(size),
+            max_size: num("MaxSize").unwrap_or(size),
+            threshold: num("Threshold").unwrap_or(2),
+        })
+    }
+
+    fn matches_size(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            DirKind::Fixed => self.size == size,
+            DirKind::Scalable => (self.min_size..=self.max_size).contains(&size),
+            DirKind::Threshold => {
+                (self.size.saturating_sub(self.threshold)..=self.size + self.threshold).contains(&size)
+            }
+        }
+    }
+
+    fn size_distance(&self, size: u32, scale: u32) -> u32 {
+        let wanted = size * scale;
+        let (low, high) = match self.kind {
+            DirKind::Fixed => (self.size, self.size),
+            DirKind::Scalable => (self.min_size, self.max_size),
+            DirKind::Threshold => (self.size.saturating_sub(self.threshold), self.size + self.threshold),
+        };
+        let (low, high) = (low * self.scale, high * self.scale);
+
+        if self.kind == DirKind::Fixed {
+            low.abs_diff(wanted)
+        } else if wanted < low {
+            low - wanted
+        } else {
+            wanted.saturating_sub(high)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Theme {
+    // Every base dir that has a directory for this theme
+    roots: Vec<PathBuf>,
+    inherits: Vec<String>,
+    dirs: Vec<ThemeDir>,
+}
+
+pub struct IconThemes {
+    base_dirs: Vec<PathBuf>,
+    current: String,
+    themes: HashMap<String, Option<Theme>>,
+}
+
+impl IconThemes {
+    pub fn new(base_dirs: Vec<PathBuf>, current: String) -> Self {
+        IconThemes {
+            base_dirs,
+            current,
+            themes: HashMap::new(),
+        }
+    }
+
+    // ~/.icons, $XDG_DATA_DIRS/icons and /usr/share/pixmaps, per the spec
+    pub fn system() -> Self {
+        let mut base_dirs: Vec<PathBuf> = home_dir().map(|h| h.join(".icons")).into_iter().collect();
+        base_dirs.extend(data_dirs().into_iter().map(|d| d.join("icons")));
+        base_dirs.push(PathBuf::from("/usr/share/pixmaps"));
+
+        let current = current_theme_name().unwrap_or_else(|| FALLBACK_THEME.to_string());
+        IconThemes::new(base_dirs, current)
+    }
+
+    fn load_theme(&self, name: &str) -> Option<Theme> {
+        let roots: Vec<PathBuf> = self
+            .base_dirs
+            .iter()
+            .map(|base| base.join(name))
+            .filter(|dir| dir.is_dir())
+            .collect();
+
+        let index = roots
+            .iter()
+            .find_map(|root| fs::read_to_string(root.join("index.theme")).ok())
+            .map(|text| parse_ini(&text))?;
+        let theme = index.get("Icon Theme")?;
+
+        let list = |key: &str| -> Vec<String> {
+            theme
+                .get(key)
+                .map(|v| v.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect())
+                .unwrap_or_default()
+        };
+
+        let mut seen = HashSet::new();
+        let dirs = list("Directories")
+            .into_iter()
+            .chain(list("ScaledDirectories"))
+            .filter(|subdir| seen.insert(subdir.clone()))
+            .filter_map(|subdir| index.get(&subdir).and_then(|group| ThemeDir::from_group(&subdir, group)))
+            .collect();
+
+        Some(Theme {
+            roots,
+            inherits: list("Inherits"),
+            dirs,
+        })
+    }
+
+    fn theme(&mut self, name: &str) -> Option<&Theme> {
+        if !self.themes.contains_key(name) {
+            let theme = self.load_theme(name);
+            self.themes.insert(name.to_string(), theme);
+        }
+        self.themes.get(name).and_then(Option::as_ref)
+    }
+
+    // The current theme, its parents depth-first, and hicolor last
+    fn theme_chain(&mut self) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut stack = vec![self.current.clone()];
+
+        while let Some(name) = stack.pop() {
+            if chain.contains(&name) || name == FALLBACK_THEME {
+                continue;
+            }
+            let Some(theme) = self.theme(&name) else {
+                continue;
+            };
+            stack.extend(theme.inherits.iter().rev().cloned());
+            chain.push(name);
+        }
+
+        chain.push(FALLBACK_THEME.to_string());
+        chain
+    }
+
+    fn lookup_in_theme(&mut self, theme_name: &str, icon: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let theme = self.theme(theme_name)?;
+        let file_in = |dir: &ThemeDir| {
+            theme.roots.iter().find_map(|root| {
+                EXTENSIONS
+                    .iter()
+                    .map(|ext| root.join(&dir.subdir).join(format!("{}.{}", icon, ext)))
+                    .find(|path| path.is_file())
+            })
+        };
+
+        if let Some(path) = theme.dirs.iter().filter(|d| d.matches_size(size, scale)).find_map(file_in) {
+            return Some(path);
+        }
+
+        theme
+            .dirs
+            .iter()
+            .filter_map(|dir| file_in(dir).map(|path| (dir.size_distance(size, scale), path)))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, path)| path)
+    }
+
+    pub fn find_icon(&mut self, icon: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        for theme in self.theme_chain() {
+            if let Some(path) = self.lookup_in_theme(&theme, icon, size, scale) {
+                return Some(path);
+            }
+        }
+
+        // Unthemed icons sitting directly in a base dir
+        self.base_dirs.iter().find_map(|base| {
+            EXTENSIONS
+                .iter()
+                .map(|ext| base.join(format!("{}.{}", icon, ext)))
+                .find(|path| path.is_file())
+        })
+    }
+}
+
+fn read_ini_key(path: PathBuf, group: &str, key: &str) -> Option<String> {
+    let text = fs::read_to_string(path).ok()?;
+    parse_ini(&text)
+        .get(group)?
+        .get(key)
+        .map(|v| v.trim_matches('"').to_string())
+        .filter(|v| !v.is_empty())
+}
+
+// Asks the desktop which icon theme is active: KDE's kdeglobals, then the
+// GNOME setting, then whatever GTK was configured with
+fn current_theme_name() -> Option<String> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|h| h.join(".config")))?;
+
+    let desktop = env::var("XDG_CURRENT_DESKTOP").unwrap_or_default().to_lowercase();
+    if desktop.contains("kde") {
+        if let Some(theme) = read_ini_key(config_dir.join("kdeglobals"), "Icons", "Theme") {
+            return Some(theme);
+        }
+    }
+
+    let from_gsettings = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "icon-theme"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().trim_matches('\'').to_string())
+        .filter(|theme| !theme.is_empty());
+    if from_gsettings.is_some() {
+        return from_gsettings;
+    }
+
+    ["gtk-4.0", "gtk-3.0"]
+        .iter()
+        .find_map(|gtk| read_ini_key(config_dir.join(gtk).join("settings.ini"), "Settings", "gtk-icon-theme-name"))
+}
+
+// Themes are read once per run; index.theme files are small but
+// get_app_icon is called for every app in the list
+fn themes() -> &'static Mutex<IconThemes> {
+    static THEMES: OnceLock<Mutex<IconThemes>> = OnceLock::new();
+    THEMES.get_or_init(|| Mutex::new(IconThemes::system()))
+}
+
+pub fn find_icon(icon: &str, size: u32) -> Option<PathBuf> {
+    themes().lock().ok()?.find_icon(icon, size, 1)
+}
+
+fn render_svg(path: &Path, size: u32) -> Result<RgbaImage, String> {
+    use resvg::{tiny_skia, usvg};
+
+    let data = fs::read(path).map_err(|e| format!("Failed to read icon: {}", e))?;
+    let tree = usvg::Tree::from_data(&data, &usvg::Options::default())
+        .map_err(|e| format!("Failed to parse SVG: {}", e))?;
+
+    let mut pixmap = tiny_skia::Pixmap::new(size, size).ok_or("Invalid icon size")?;
+
+    // Fit the document into the square, centred, keeping its aspect ratio
+    let doc = tree.size();
+    let scale = (size as f32 / doc.width()).min(size as f32 / doc.height());
+    let transform = tiny_skia::Transform::from_translate(
+        (size as f32 - doc.width() * scale) / 2.0,
+        (size as f32 - doc.height() * scale) / 2.0,
+    )
+    .pre_scale(scale, scale);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia stores premultiplied alpha
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(size, size, pixels).ok_or_else(|| "Failed to build icon image".to_string())
+}
+
+// Loads any icon file as a size×size RGBA image
+pub fn load_icon(path: &Path, size: u32) -> Result<RgbaImage, String> {
+    let is_svg = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("svg") || e.eq_ignore_ascii_case("svgz"));
+    if is_svg {
+        return render_svg(path, size);
+    }
+
+    let img = image::open(path).map_err(|e| format!("Failed to load icon: {}", e))?;
+    if img.width() == size && img.height() == size {
+        return Ok(img.to_rgba8());
+    }
+    Ok(img.resize_exact(size, size, FilterType::Lanczos3).to_rgba8())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: PathBuf, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn lookup_follows_inheritance_and_sizes() {
+        let root = env::temp_dir().join(format!("focuslock-icons-{}", std::process::id()));
+        let user = root.join("home/.icons");
+        let system = root.join("usr/share/icons");
+
+        write(
+            system.join("Child/index.theme"),
+            "[Icon Theme]\nInherits=Parent\nDirectories=48x48/apps\n\n[48x48/apps]\nSize=48\nType=Fixed\n",
+        );
+        write(
+            system.join("Parent/index.theme"),
+            "[Icon Theme]\nDirectories=16x16/apps,scalable/apps\n\n[16x16/apps]\nSize=16\nType=Fixed\n\n\
+             [scalable/apps]\nSize=128\nMinSize=8\nMaxSize=512\nType=Scalable\n",
+        );
+        write(
+            system.join("hicolor/index.theme"),
+            "[Icon Theme]\nDirectories=32x32/apps\n\n[32x32/apps]\nSize=32\n",
+        );
+        write(system.join("Parent/16x16/apps/editor.png"), "");
+        write(system.join("Parent/scalable/apps/editor.svg"), "");
+        write(system.join("hicolor/32x32/apps/legacy.png"), "");
+        // A user copy of the theme dir shadows nothing but adds icons
+        write(user.join("Child/48x48/apps/mail.png"), "");
+
+        let mut themes = IconThemes::new(vec![user.clone(), system.clone()], "Child".to_string());
+
+        assert_eq!(themes.find_icon("mail", 48, 1), Some(user.join("Child/48x48/apps/mail.png")));
+        assert_eq!(themes.find_icon("editor", 64, 1), Some(system.join("Parent/scalable/apps/editor.svg")));
+        assert_eq!(themes.find_icon("legacy", 48, 1), Some(system.join("hicolor/32x32/apps/legacy.png")));
+        assert_eq!(themes.find_icon("missing", 48, 1), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn svg_renders_at_requested_size() {
+        let path = env::temp_dir().join(format!("focuslock-icon-{}.svg", std::process::id()));
+        fs::write(
+            &path,
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16"><rect width="16" height="16" fill="#ff0000"/></svg>"##,
+        )
+        .unwrap();
+
+        let img = load_icon(&path, 64).unwrap();
+        assert_eq!(img.dimensions(), (64, 64));
+        assert_eq!(img.get_pixel(32, 32).0, [255, 0, 0, 255]);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -7,8 +7,36 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tauri::{Manager, State};
 
+mod browser_bridge;
 mod db;
-use db::DbState;
+mod early_end;
+#[cfg(target_os = "linux")]
+mod desktop_entries;
+mod dns;
+mod domains;
+mod enforcement;
+mod error;
+mod freeze;
+mod hosts;
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+mod icon_cache;
+#[cfg(target_os = "linux")]
+mod icons;
+mod migrations;
+mod passphrase;
+mod pomodoro;
+#[cfg(target_os = "linux")]
+mod procfs;
+mod profiles;
+mod schedule;
+mod session;
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+mod soft_block;
+mod stats;
+mod strict;
+use db::{DbState, SessionStatus};
+use error::{CommandResult, FocusLockError};
+use passphrase::PassphraseState;
 
 // Global monitor state
 struct MonitorState {
@@ -24,7 +52,7 @@ struct AppInfo {
 
 #[cfg(target_os = "windows")]
 #[tauri::command]
-async fn get_installed_apps() -> Result<Vec<AppInfo>, String> {
+async fn get_installed_apps() -> CommandResult<Vec<AppInfo>> {
     use winreg::enums::*;
     use winreg::RegKey;
     
@@ -79,13 +107,12 @@ async fn get_installed_apps() -> Result<Vec<AppInfo>, String> {
         
         Ok(apps)
     })
-    .await
-    .map_err(|e| format!("Task error: {}", e))?
+    .await?
 }
 
 #[cfg(target_os = "windows")]
 #[tauri::command]
-async fn get_running_processes() -> Result<Vec<AppInfo>, String> {
+async fn get_running_processes() -> CommandResult<Vec<AppInfo>> {
     use sysinfo::{System, ProcessesToUpdate};
     
     // Run in blocking thread using Tauri's runtime, only refresh processes
@@ -124,25 +151,80 @@ async fn get_running_processes() -> Result<Vec<AppInfo>, String> {
         
         Ok(processes)
     })
-    .await
-    .map_err(|e| format!("Task error: {}", e))?
+    .await?
 }
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(target_os = "linux")]
 #[tauri::command]
-fn get_installed_apps() -> Result<Vec<AppInfo>, String> {
-    Err("This feature is only available on Windows".to_string())
+async fn get_installed_apps() -> CommandResult<Vec<AppInfo>> {
+    // Walking every applications dir and parsing each entry is disk-bound
+    tauri::async_runtime::spawn_blocking(|| {
+        let mut seen_names = HashSet::new();
+
+        let mut apps: Vec<AppInfo> = desktop_entries::installed_apps()
+            .into_iter()
+            .filter(|app| seen_names.insert(app.name.to_lowercase()))
+            .map(|app| AppInfo {
+                name: app.name,
+                path: app.exec_path,
+                icon: app.icon,
+            })
+            .collect();
+
+        // Sort alphabetically
+        apps.sort_by_key(|a| a.name.to_lowercase());
+
+        Ok(apps)
+    })
+    .await?
 }
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 #[tauri::command]
-fn get_running_processes() -> Result<Vec<AppInfo>, String> {
-    Err("This feature is only available on Windows".to_string())
+fn get_installed_apps() -> CommandResult<Vec<AppInfo>> {
+    Err(FocusLockError::Unsupported("Listing installed apps is only available on Windows and Linux".to_string()))
+}
+
+#[cfg(target_os = "linux")]
+#[tauri::command]
+async fn get_running_processes() -> CommandResult<Vec<AppInfo>> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let mut processes = Vec::new();
+        let mut seen_names = HashSet::new();
+
+        for entry in procfs::list_user_processes() {
+            let name = entry.name();
+
+            if name.is_empty() || seen_names.contains(&name) {
+                continue;
+            }
+
+            seen_names.insert(name.clone());
+
+            processes.push(AppInfo {
+                name,
+                path: entry.exe_path,
+                icon: None,
+            });
+        }
+
+        // Sort alphabetically
+        processes.sort_by_key(|p| p.name.to_lowercase());
+
+        Ok(processes)
+    })
+    .await?
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+#[tauri::command]
+fn get_running_processes() -> CommandResult<Vec<AppInfo>> {
+    Err(FocusLockError::Unsupported("Listing processes is only available on Windows and Linux".to_string()))
 }
 
 #[cfg(target_os = "windows")]
 #[tauri::command]
-async fn kill_process(process_name: String) -> Result<String, String> {
+async fn kill_process(process_name: String) -> CommandResult<String> {
     use sysinfo::{System, ProcessesToUpdate, Signal};
     
     tauri::async_runtime::spawn_blocking(move || {
@@ -165,246 +247,311 @@ async fn kill_process(process_name: String) -> Result<String, String> {
         if killed_count > 0 {
             Ok(format!("Killed {} instance(s) of {}", killed_count, process_name))
         } else {
-            Err(format!("Process '{}' not found", process_name))
+            Err(FocusLockError::NotFound {
+                entity: format!("Process '{}'", process_name),
+                id: None,
+            })
         }
     })
-    .await
-    .map_err(|e| format!("Task error: {}", e))?
+    .await?
 }
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(target_os = "linux")]
 #[tauri::command]
-fn kill_process(_process_name: String) -> Result<String, String> {
-    Err("This feature is only available on Windows".to_string())
+async fn kill_process(process_name: String) -> CommandResult<String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut killed_count = 0;
+
+        for entry in procfs::list_user_processes() {
+            // Match process name (case-insensitive)
+            let matches = entry.name().eq_ignore_ascii_case(&process_name)
+                || entry.comm.eq_ignore_ascii_case(&process_name);
+
+            if matches && procfs::kill(entry.pid) {
+                killed_count += 1;
+            }
+        }
+
+        if killed_count > 0 {
+            Ok(format!("Killed {} instance(s) of {}", killed_count, process_name))
+        } else {
+            Err(FocusLockError::NotFound {
+                entity: format!("Process '{}'", process_name),
+                id: None,
+            })
+        }
+    })
+    .await?
 }
 
-// Extract icon from application path and return as base64 PNG
-#[cfg(target_os = "windows")]
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 #[tauri::command]
-async fn get_app_icon(app_path: String) -> Result<String, String> {
-    use std::path::Path;
-    use image::ImageEncoder;
-    use exeico::get_exe_ico;
-    
-    // Extract the actual exe path from DisplayIcon format
-    // DisplayIcon can be "C:\path\to\app.exe,0" or just "C:\path\to\app.exe"
-    let exe_path = app_path.split(',').next().unwrap_or(&app_path).trim_matches('"');
-    
-    if !Path::new(exe_path).exists() {
-        return Err(format!("File not found: {}", exe_path));
+fn kill_process(_process_name: String) -> CommandResult<String> {
+    Err(FocusLockError::Unsupported("Killing processes is only available on Windows and Linux".to_string()))
+}
+
+// Find the file an icon hint refers to
+// DisplayIcon can be "C:\path\to\app.exe,0" or just "C:\path\to\app.exe"
+#[cfg(target_os = "windows")]
+fn icon_source(app_path: &str, _size: u32) -> CommandResult<std::path::PathBuf> {
+    let exe_path = app_path.split(',').next().unwrap_or(app_path).trim_matches('"');
+
+    if !std::path::Path::new(exe_path).exists() {
+        return Err(FocusLockError::NotFound {
+            entity: format!("File {}", exe_path),
+            id: None,
+        });
     }
-    
-    // Try to extract the real icon from the exe
-    match get_exe_ico(exe_path) {
-        Ok(ico_data) => {
-            // Convert ICO to PNG
-            match image::load_from_memory(&ico_data) {
-                Ok(img) => {
-                    // Resize to 32x32 for consistency
-                    let resized = img.resize_exact(32, 32, image::imageops::FilterType::Lanczos3);
-                    
-                    // Encode to PNG
-                    let mut png_data = Vec::new();
-                    let encoder = image::codecs::png::PngEncoder::new(&mut png_data);
-                    let rgba = resized.to_rgba8();
-                    encoder.write_image(&rgba, 32, 32, image::ExtendedColorType::Rgba8)
-                        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
-                    
-                    let base64_image = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &png_data);
-                    Ok(format!("data:image/png;base64,{}", base64_image))
-                }
-                Err(_) => {
-                    // If ICO loading failed, just return the raw ICO data as base64
-                    let base64_image = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &ico_data);
-                    Ok(format!("data:image/x-icon;base64,{}", base64_image))
-                }
-            }
-        }
-        Err(e) => {
-            Err(format!("Failed to extract icon: {}", e))
+    Ok(std::path::PathBuf::from(exe_path))
+}
+
+// Extract the real icon from the exe as PNG bytes
+#[cfg(target_os = "windows")]
+fn render_icon(source: &std::path::Path, size: u32) -> Result<Vec<u8>, String> {
+    use exeico::get_exe_ico;
+
+    let exe_path = source.to_string_lossy();
+    let ico_data = get_exe_ico(exe_path.as_ref()).map_err(|e| format!("Failed to extract icon: {}", e))?;
+
+    match image::load_from_memory(&ico_data) {
+        Ok(img) => {
+            let resized = img.resize_exact(size, size, image::imageops::FilterType::Lanczos3);
+            icon_cache::encode_png(&resized.to_rgba8())
         }
+        // If ICO loading failed, just return the raw ICO data
+        Err(_) => Ok(ico_data),
     }
 }
 
+// icon_hint could be an icon name or an absolute path
 #[cfg(target_os = "linux")]
-#[tauri::command]
-async fn get_app_icon(icon_hint: String) -> Result<String, String> {
-    use std::path::PathBuf;
-    use image::ImageEncoder;
-    
-    // icon_hint could be an icon name or path
+fn icon_source(icon_hint: &str, size: u32) -> CommandResult<std::path::PathBuf> {
     let icon_path = if icon_hint.starts_with('/') {
-        // Absolute path
-        PathBuf::from(&icon_hint)
+        std::path::PathBuf::from(icon_hint)
     } else {
-        // Icon name - search standard locations
-        find_linux_icon(&icon_hint).ok_or("Icon not found")?
+        // Icon name - resolve through the current icon theme
+        icons::find_icon(icon_hint, size).ok_or_else(|| FocusLockError::NotFound {
+            entity: format!("Icon '{}'", icon_hint),
+            id: None,
+        })?
     };
-    
+
     if !icon_path.exists() {
-        return Err(format!("Icon file not found: {:?}", icon_path));
+        return Err(FocusLockError::NotFound {
+            entity: format!("Icon file {}", icon_path.display()),
+            id: None,
+        });
     }
-    
-    // Load the icon image
-    let img = image::open(&icon_path)
-        .map_err(|e| format!("Failed to load icon: {}", e))?;
-    
-    // Resize to 32x32 for consistency
-    let resized = img.resize_exact(32, 32, image::imageops::FilterType::Lanczos3);
-    
-    // Encode to PNG
-    let mut png_data = Vec::new();
-    let encoder = image::codecs::png::PngEncoder::new(&mut png_data);
-    let rgba = resized.to_rgba8();
-    encoder.write_image(&rgba, 32, 32, image::ExtendedColorType::Rgba8)
-        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
-    
-    let base64_image = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &png_data);
-    Ok(format!("data:image/png;base64,{}", base64_image))
+    Ok(icon_path)
 }
 
+// Rasterizes SVGs and resizes bitmaps to the requested size
 #[cfg(target_os = "linux")]
-fn find_linux_icon(icon_name: &str) -> Option<PathBuf> {
-    let sizes = vec![48, 64, 128, 256, 32];
-    let formats = vec!["png", "svg", "xpm"];
-    let home = std::env::var("HOME").unwrap_or_else(|_| "/root".to_string());
-    
-    // Search icon theme paths in order of priority
-    for size in &sizes {
-        for fmt in &formats {
-            let paths = vec![
-                format!("/usr/share/icons/hicolor/{}x{}/apps/{}.{}", size, size, icon_name, fmt),
-                format!("{}/.local/share/icons/hicolor/{}x{}/apps/{}.{}", home, size, size, icon_name, fmt),
-                format!("/usr/share/icons/gnome/{}x{}/apps/{}.{}", size, size, icon_name, fmt),
-            ];
-            
-            for path in paths {
-                let p = PathBuf::from(&path);
-                if p.exists() {
-                    return Some(p);
-                }
-            }
-        }
-    }
-    
-    // Check pixmaps as fallback
-    for fmt in &formats {
-        let paths = vec![
-            format!("/usr/share/pixmaps/{}.{}", icon_name, fmt),
-            format!("{}/.local/share/pixmaps/{}.{}", home, icon_name, fmt),
-        ];
-        
-        for path in paths {
-            let p = PathBuf::from(&path);
-            if p.exists() {
-                return Some(p);
-            }
-        }
-    }
-    
-    None
+fn render_icon(source: &std::path::Path, size: u32) -> Result<Vec<u8>, String> {
+    icon_cache::encode_png(&icons::load_icon(source, size)?)
 }
 
-#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn cached_app_icon(cache: &icon_cache::IconCache, app_path: &str, size: u32) -> CommandResult<String> {
+    let source = icon_source(app_path, size)?;
+    let data = cache
+        .get_or_render(&source, size, || render_icon(&source, size))
+        .map_err(FocusLockError::Platform)?;
+    Ok(icon_cache::data_url(&data))
+}
+
+// Return an app's icon as a data URL, `size` pixels square (default 32)
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 #[tauri::command]
-async fn get_app_icon(_app_path: String) -> Result<String, String> {
-    Err("Icon extraction not implemented on this platform".to_string())
+async fn get_app_icon(app: tauri::AppHandle, app_path: String, size: Option<u32>) -> CommandResult<String> {
+    let size = icon_cache::icon_size(size)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let cache: State<icon_cache::IconCache> = app.state();
+        cached_app_icon(&cache, &app_path, size)
+    })
+    .await?
 }
 
-#[cfg(target_os = "windows")]
+// Batch version of get_app_icon; paths without an icon are left out
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 #[tauri::command]
-async fn start_session_monitor(
+async fn get_app_icons(
     app: tauri::AppHandle,
-    monitor: State<'_, MonitorState>,
-) -> Result<String, String> {
-    use sysinfo::{System, ProcessesToUpdate, Signal};
+    paths: Vec<String>,
+    size: Option<u32>,
+) -> CommandResult<std::collections::HashMap<String, String>> {
+    let size = icon_cache::icon_size(size)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let cache: State<icon_cache::IconCache> = app.state();
+        let icons = paths
+            .into_iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter_map(|path| {
+                let icon = cached_app_icon(&cache, &path, size).ok()?;
+                Some((path, icon))
+            })
+            .collect();
+        Ok(icons)
+    })
+    .await?
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+#[tauri::command]
+async fn get_app_icon(_app_path: String, _size: Option<u32>) -> CommandResult<String> {
+    Err(FocusLockError::Unsupported("Icon extraction not implemented on this platform".to_string()))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+#[tauri::command]
+async fn get_app_icons(
+    _paths: Vec<String>,
+    _size: Option<u32>,
+) -> CommandResult<std::collections::HashMap<String, String>> {
+    Err(FocusLockError::Unsupported("Icon extraction not implemented on this platform".to_string()))
+}
+
+// Starts the enforcement loop unless it is already running. Shared by the
+// start_session_monitor command, the session engine and crash recovery.
+// Returns false if a loop was already active.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn launch_session_monitor(app: &tauri::AppHandle) -> bool {
+    use enforcement::{Action, Enforcer, FailureTracker, PlatformSource};
+    use std::sync::Mutex;
     use std::time::Duration;
     
+    let monitor: State<MonitorState> = app.state();
+    
     // If already running, don't start again
-    if monitor.is_running.load(Ordering::Relaxed) {
-        return Ok("Monitor already running".to_string());
+    if monitor.is_running.swap(true, Ordering::Relaxed) {
+        return false;
     }
     
-    monitor.is_running.store(true, Ordering::Relaxed);
     let is_running = monitor.is_running.clone();
+    let app = app.clone();
     
     // Spawn background monitoring task
     tauri::async_runtime::spawn(async move {
         println!("[Monitor] Starting session monitor loop");
         
+        // Kept across ticks so platform sources can reuse their process tables
+        let enforcer = Arc::new(Mutex::new(Enforcer::new(PlatformSource::new())));
+        
+        // Soft-blocked processes are seen on every tick; warn again only after
+        // the notification cooldown
+        let mut warned = soft_block::SoftBlockTracker::new();
+        // Processes that survive a kill or freeze are logged once, not every tick
+        let mut failures = FailureTracker::new();
+        
         while is_running.load(Ordering::Relaxed) {
             // Get database state from app handle
             let db: State<DbState> = app.state();
             
             // Get active sessions
             if let Ok(sessions) = db::get_sessions(db.clone()) {
-                let has_active_session = sessions.iter().any(|s| s.status == "running");
-                
-                if !has_active_session {
+                let Some(active_session) = sessions.into_iter().find(|s| s.status == SessionStatus::Running) else {
                     // No active sessions - stop monitoring
                     println!("[Monitor] No active sessions, stopping monitor");
                     is_running.store(false, Ordering::Relaxed);
                     break;
+                };
+                let active_session_id = Some(active_session.id.clone());
+                
+                // Pomodoro breaks leave everything unblocked until the next work interval
+                if !pomodoro::enforcing(&db, &active_session, session::now_utc()) {
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                    continue;
                 }
                 
-                // Get block rules
-                let db_for_rules: State<DbState> = app.state();
-                if let Ok(rules) = db::get_block_rules(db_for_rules) {
-                    let rules_clone = rules.clone();
+                // Get the rules of the session's profile and how to apply them
+                let (policy, cadence, frozen_pids) = match db.conn.lock() {
+                    Ok(conn) => (
+                        profiles::policy_for(&conn, active_session.profile_id.as_deref()).ok(),
+                        soft_block::Cadence::load(&conn),
+                        freeze::frozen_pids(&conn).unwrap_or_default(),
+                    ),
+                    Err(_) => (None, soft_block::Cadence::Normal, Vec::new()),
+                };
+                if let Some(policy) = policy {
+                    let enforcer = enforcer.clone();
+                    
+                    // Enumerate and signal processes in blocking thread
+                    let enforcements = tauri::async_runtime::spawn_blocking(move || {
+                        match enforcer.lock() {
+                            Ok(mut enforcer) => {
+                                // Re-read every tick so thawed pids are enforced again
+                                enforcer.set_frozen(frozen_pids);
+                                enforcer.enforce(&policy)
+                            }
+                            Err(_) => Vec::new(),
+                        }
+                    })
+                    .await
+                    .unwrap_or_default();
+                    
+                    let now = session::now_utc();
+                    let mut events = Vec::new();
+                    let mut frozen = Vec::new();
                     
-                    // Get running processes in blocking thread
-                    if let Ok(()) = tauri::async_runtime::spawn_blocking(move || {
-                        let mut sys = System::new();
-                        sys.refresh_processes(ProcessesToUpdate::All);
+                    for e in enforcements {
+                        if !failures.should_report(&e, now) {
+                            continue;
+                        }
+                        match e.action {
+                            Action::Killed => println!("[Monitor] HARD BLOCK - Killed: {} (pid {}, rule: {} [{}])", e.process.name, e.process.pid, e.rule_app_id, e.rule_id.as_deref().unwrap_or("allowlist")),
+                            Action::KillFailed => println!("[Monitor] HARD BLOCK - Failed to kill: {} (pid {}, rule: {} [{}])", e.process.name, e.process.pid, e.rule_app_id, e.rule_id.as_deref().unwrap_or("allowlist")),
+                            Action::Frozen => {
+                                println!("[Monitor] FREEZE - Suspended: {} (pid {}, rule: {} [{}])", e.process.name, e.process.pid, e.rule_app_id, e.rule_id.as_deref().unwrap_or("allowlist"));
+                                frozen.push((e.process.pid, e.process.name.clone()));
+                            }
+                            Action::FreezeFailed => println!("[Monitor] FREEZE - Failed to suspend: {} (pid {}, rule: {} [{}])", e.process.name, e.process.pid, e.rule_app_id, e.rule_id.as_deref().unwrap_or("allowlist")),
+                            Action::Warned => {
+                                if !warned.should_warn(e.process.pid, e.rule_id.as_deref(), now, cadence) {
+                                    continue;
+                                }
+                                println!("[Monitor] SOFT BLOCK - Warning: {} is running but not blocked (rule: {} [{}])", e.process.name, e.rule_app_id, e.rule_id.as_deref().unwrap_or("allowlist"));
+                                soft_block::warn(&app, cadence, soft_block::SoftBlock {
+                                    session_id: active_session_id.clone(),
+                                    rule_id: e.rule_id.clone(),
+                                    rule_app_id: e.rule_app_id.clone(),
+                                    process_name: e.process.name.clone(),
+                                    pid: e.process.pid,
+                                    timestamp_utc: now,
+                                });
+                            }
+                        }
                         
-                        for (_, process) in sys.processes() {
-                            let process_name = process.name().to_string_lossy().to_string();
-                            let process_exe_path = process.exe()
-                                .and_then(|p| p.to_str())
-                                .unwrap_or("");
-                            
-                            // Check if process matches any block rule
-                            for rule in &rules_clone {
-                                // Extract exe name from rule's app_id (could be path or exe name)
-                                let rule_exe_name = if rule.app_id.contains('\\') || rule.app_id.contains('/') {
-                                    // It's a path - extract the last component and add .exe if missing
-                                    let path_parts: Vec<&str> = rule.app_id.split(&['\\', '/'][..]).collect();
-                                    let last_part = path_parts.last().unwrap_or(&"");
-                                    if last_part.to_lowercase().ends_with(".exe") {
-                                        last_part.to_string()
-                                    } else {
-                                        format!("{}.exe", last_part)
-                                    }
-                                } else {
-                                    // Already an exe name
-                                    if rule.app_id.to_lowercase().ends_with(".exe") {
-                                        rule.app_id.clone()
-                                    } else {
-                                        format!("{}.exe", rule.app_id)
-                                    }
-                                };
-                                
-                                let matches = match rule.match_kind.as_str() {
-                                    "exe" => process_name.eq_ignore_ascii_case(&rule_exe_name),
-                                    "path" => process_exe_path.to_lowercase().contains(&rule.app_id.to_lowercase()),
-                                    _ => process_name.to_lowercase().contains(&rule_exe_name.to_lowercase()),
-                                };
-                                
-                                if matches {
-                                    if rule.mode == "hard" {
-                                        // Hard mode: Kill the process
-                                        if process.kill_with(Signal::Kill).is_some() {
-                                            println!("[Monitor] HARD BLOCK - Killed: {} (rule: {})", process_name, rule.app_id);
-                                        }
-                                    } else if rule.mode == "soft" {
-                                        // Soft mode: Log warning only
-                                        println!("[Monitor] SOFT BLOCK - Warning: {} is running but not blocked (rule: {})", process_name, rule.app_id);
-                                    }
+                        events.push(db::NewBlockEvent {
+                            timestamp_utc: now,
+                            session_id: active_session_id.clone(),
+                            rule_id: e.rule_id,
+                            process_name: e.process.name,
+                            exe_path: e.process.exe_path,
+                            pid: Some(e.process.pid as i64),
+                            action: e.action.as_str().to_string(),
+                            count: 1,
+                        });
+                    }
+                    
+                    // Persist frozen pids first so a crash can't strand them
+                    if !frozen.is_empty() {
+                        if let Ok(conn) = db.conn.lock() {
+                            for (pid, name) in &frozen {
+                                if let Err(e) = freeze::record_frozen(&conn, &active_session.id, *pid, name, now) {
+                                    println!("[Monitor] Failed to record frozen process {}: {}", pid, e);
                                 }
                             }
                         }
-                    }).await {
-                        // Successfully checked processes
+                    }
+                    
+                    if !events.is_empty() {
+                        let db: State<DbState> = app.state();
+                        if let Err(e) = db::record_block_events(&db, &events) {
+                            println!("[Monitor] Failed to record block events: {}", e);
+                        }
                     }
                 }
             } else {
@@ -423,23 +570,53 @@ async fn start_session_monitor(
         println!("[Monitor] Session monitor loop stopped");
     });
     
-    Ok("Session monitor started".to_string())
+    true
 }
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn launch_session_monitor(_app: &tauri::AppHandle) -> bool {
+    false
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+#[tauri::command]
+async fn start_session_monitor(app: tauri::AppHandle) -> CommandResult<String> {
+    if launch_session_monitor(&app) {
+        Ok("Session monitor started".to_string())
+    } else {
+        Ok("Monitor already running".to_string())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 #[tauri::command]
-fn start_session_monitor() -> Result<String, String> {
-    Err("This feature is only available on Windows".to_string())
+fn start_session_monitor() -> CommandResult<String> {
+    Err(FocusLockError::Unsupported("The session monitor is only available on Windows and Linux".to_string()))
 }
 
 #[tauri::command]
-fn stop_session_monitor(monitor: State<MonitorState>) -> Result<String, String> {
+fn stop_session_monitor(
+    monitor: State<MonitorState>,
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    unlock_token: Option<String>,
+) -> CommandResult<String> {
+    {
+        let conn = db.conn.lock()?;
+        strict::ensure_unlocked(&conn, "stop the session monitor")?;
+        passphrase.ensure_authorized(&conn, unlock_token.as_deref(), "stop the session monitor")?;
+        // Stopping enforcement mid-session is ending it early
+        if strict::session_active(&conn)? {
+            early_end::ensure_no_cooldown(&conn)?;
+        }
+    }
     monitor.is_running.store(false, Ordering::Relaxed);
     Ok("Session monitor stopped".to_string())
 }
 
 fn main() {
     tauri::Builder::default()
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
             // Initialize SQLite database in app data directory
             let app_data_dir = app.path().app_data_dir()
@@ -453,6 +630,15 @@ fn main() {
             let db_state = DbState::new(db_path.to_str().unwrap())
                 .expect("Failed to initialize database");
             
+            // Reconcile sessions left running by a crash or reboot
+            let resume_monitor = match session::recover_sessions(&db_state) {
+                Ok(still_running) => still_running,
+                Err(e) => {
+                    println!("[Session] Recovery failed: {}", e);
+                    false
+                }
+            };
+            
             // Initialize monitor state
             let monitor_state = MonitorState {
                 is_running: Arc::new(AtomicBool::new(false)),
@@ -460,6 +646,27 @@ fn main() {
             
             app.manage(db_state);
             app.manage(monitor_state);
+            app.manage(PassphraseState::new());
+            app.manage(hosts::HostsBlocker::new(&app_data_dir));
+            app.manage(dns::DnsFilter::new());
+
+            // Rendered icons survive restarts; see icon_cache.rs
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            {
+                let icon_cache = icon_cache::IconCache::new(app_data_dir.join("icon-cache"))
+                    .expect("Failed to create icon cache directory");
+                app.manage(icon_cache);
+            }
+            
+            // Drive session countdowns from the backend
+            session::spawn_ticker(app.handle().clone());
+            schedule::spawn_scheduler(app.handle().clone());
+            hosts::spawn_sync(app.handle().clone());
+            dns::spawn_filter(app.handle().clone());
+            
+            if resume_monitor {
+                launch_session_monitor(app.handle());
+            }
             
             Ok(())
         })
@@ -468,6 +675,7 @@ fn main() {
             get_running_processes,
             kill_process,
             get_app_icon,
+            get_app_icons,
             start_session_monitor,
             stop_session_monitor,
             // Database commands
@@ -478,11 +686,43 @@ fn main() {
             db::create_block_rule,
             db::update_block_rule,
             db::delete_block_rule,
+            profiles::get_block_profiles,
+            profiles::create_block_profile,
+            profiles::update_block_profile,
+            profiles::delete_block_profile,
             db::get_sessions,
             db::create_session,
             db::update_session,
+            db::get_session_interruptions,
+            db::get_block_events,
+            stats::get_focus_stats,
+            schedule::get_schedules,
+            schedule::create_schedule,
+            schedule::update_schedule,
+            schedule::delete_schedule,
+            schedule::get_upcoming_occurrences,
+            schedule::skip_occurrence,
+            session::start_session,
+            session::pause_session,
+            session::resume_session,
+            session::cancel_session,
+            session::complete_session,
+            early_end::request_early_end,
+            early_end::confirm_early_end,
+            early_end::withdraw_early_end,
+            early_end::get_early_end_requests,
+            pomodoro::start_pomodoro_session,
+            pomodoro::get_pomodoro_status,
+            pomodoro::get_session_intervals,
             db::get_settings,
             db::upsert_setting,
+            passphrase::get_passphrase_status,
+            passphrase::verify_passphrase,
+            passphrase::set_passphrase,
+            dns::get_dns_filter_status,
+            hosts::get_hosts_status,
+            browser_bridge::install_browser_bridge,
+            browser_bridge::uninstall_browser_bridge,
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
@@ -0,0 +1,427 @@
+// Versioned schema migrations for focuslock.db
+//
+// The schema version lives in SQLite's `user_version` pragma. Each step runs
+// in its own transaction and bumps the version as its last statement, so a
+// failure leaves the database at the previous version instead of half
+// migrated. Steps are append-only: never edit one that has shipped, add a new
+// one instead.
+
+use rusqlite::{Connection, Result as SqliteResult, Transaction};
+use std::path::{Path, PathBuf};
+
+struct Migration {
+    version: i32,
+    description: &'static str,
+    up: fn(&Transaction) -> SqliteResult<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "favorites, block_rules, sessions and settings tables",
+        up: create_base_tables,
+    },
+    Migration {
+        version: 2,
+        description: "normalize block rule match_kind/mode values",
+        up: normalize_block_rules,
+    },
+    Migration {
+        version: 3,
+        description: "session heartbeats and interruptions",
+        up: create_session_recovery_tables,
+    },
+    Migration {
+        version: 4,
+        description: "block_events log",
+        up: create_block_events,
+    },
+    Migration {
+        version: 5,
+        description: "recurring session schedules",
+        up: create_session_schedules,
+    },
+    Migration {
+        version: 6,
+        description: "pomodoro sessions and their work/break intervals",
+        up: create_pomodoro_tables,
+    },
+    Migration {
+        version: 7,
+        description: "block profiles, with profile_id on rules and sessions",
+        up: create_block_profiles,
+    },
+    Migration {
+        version: 8,
+        description: "allowlist mode on block profiles",
+        up: add_profile_list_mode,
+    },
+    Migration {
+        version: 9,
+        description: "processes frozen by freeze rules",
+        up: create_frozen_processes,
+    },
+    Migration {
+        version: 10,
+        description: "early end requests",
+        up: create_early_end_requests,
+    },
+    Migration {
+        version: 11,
+        description: "block profile for recurring schedules",
+        up: add_schedule_profile,
+    },
+    Migration {
+        version: 12,
+        description: "repeat count on block events",
+        up: add_block_event_count,
+    },
+];
+
+pub fn latest_version() -> i32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn user_version(conn: &Connection) -> SqliteResult<i32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+fn backup_path(db_path: &Path, version: i32) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    db_path.with_file_name(name)
+}
+
+// Upgrades `conn` to the latest schema. When `db_path` is given and the file
+// already holds data, a copy is written next to it (`focuslock.db.v<N>.bak`)
+// before the first step runs.
+pub fn migrate(conn: &mut Connection, db_path: Option<&Path>) -> SqliteResult<()> {
+    let current = user_version(conn)?;
+    if current >= latest_version() {
+        return Ok(());
+    }
+
+    let has_tables: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if let (Some(db_path), true) = (db_path, has_tables > 0) {
+        let backup = backup_path(db_path, current);
+        if !backup.exists() {
+            // VACUUM INTO gives a consistent copy even with the file open
+            conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+            println!("[DB] Backed up database to {:?} before migrating", backup);
+        }
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        println!("[DB] Migrating to v{}: {}", migration.version, migration.description);
+
+        let tx = conn.transaction()?;
+        (migration.up)(&tx)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+// v1: the original schema. Uses IF NOT EXISTS so databases created before
+// versioning (user_version 0 but tables present) are adopted as-is.
+fn create_base_tables(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS favorites (
+            id TEXT PRIMARY KEY,
+            app_id TEXT NOT NULL,
+            display_name TEXT NOT NULL,
+            pinned_order INTEGER,
+            icon_hint TEXT
+        );
+        CREATE TABLE IF NOT EXISTS block_rules (
+            id TEXT PRIMARY KEY,
+            app_id TEXT NOT NULL,
+            match_kind TEXT NOT NULL,
+            mode TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS sessions (
+            id TEXT PRIMARY KEY,
+            start_utc INTEGER NOT NULL,
+            end_utc INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            duration_secs INTEGER NOT NULL,
+            remaining_secs INTEGER
+        );
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );",
+    )
+}
+
+// v2: rules written before match_kind/mode were validated may use any casing
+// or stray whitespace. Fix those in place, and move anything still
+// unrecognised into invalid_block_rules so it stops breaking get_block_rules
+// but is kept for the user to review. The value lists are spelled out rather
+// than taken from the enums so this step stays fixed when variants are added.
+fn normalize_block_rules(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS invalid_block_rules (
+            id TEXT PRIMARY KEY,
+            app_id TEXT NOT NULL,
+            match_kind TEXT NOT NULL,
+            mode TEXT NOT NULL,
+            reason TEXT NOT NULL
+        );
+        UPDATE block_rules
+           SET match_kind = lower(trim(match_kind)), mode = lower(trim(mode))
+         WHERE match_kind != lower(trim(match_kind)) OR mode != lower(trim(mode));
+        INSERT OR REPLACE INTO invalid_block_rules (id, app_id, match_kind, mode, reason)
+            SELECT id, app_id, match_kind, mode,
+                   CASE WHEN match_kind NOT IN ('exe', 'package', 'lnk', 'path', 'regex') THEN 'unknown match_kind'
+                        ELSE 'unknown mode' END
+              FROM block_rules
+             WHERE match_kind NOT IN ('exe', 'package', 'lnk', 'path', 'regex')
+                OR mode NOT IN ('hard', 'soft');
+        DELETE FROM block_rules
+         WHERE match_kind NOT IN ('exe', 'package', 'lnk', 'path', 'regex')
+            OR mode NOT IN ('hard', 'soft');",
+    )
+}
+
+// v3: bookkeeping for crash/reboot recovery of running sessions
+fn create_session_recovery_tables(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_heartbeats (
+            session_id TEXT PRIMARY KEY,
+            last_seen_utc INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS session_interruptions (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            started_utc INTEGER NOT NULL,
+            ended_utc INTEGER NOT NULL,
+            reason TEXT NOT NULL
+        );",
+    )
+}
+
+// v4: one row per process the monitor acted on
+fn create_block_events(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS block_events (
+            id TEXT PRIMARY KEY,
+            timestamp_utc INTEGER NOT NULL,
+            session_id TEXT,
+            rule_id TEXT,
+            process_name TEXT NOT NULL,
+            exe_path TEXT,
+            pid INTEGER,
+            action TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_block_events_timestamp ON block_events (timestamp_utc);
+        CREATE INDEX IF NOT EXISTS idx_block_events_session ON block_events (session_id, timestamp_utc);
+        CREATE INDEX IF NOT EXISTS idx_block_events_rule ON block_events (rule_id, timestamp_utc);",
+    )
+}
+
+// v5: recurring schedules, and which of their occurrences were started or
+// skipped so the scheduler never fires one twice
+fn create_session_schedules(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_schedules (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            days TEXT NOT NULL,
+            start_time TEXT NOT NULL,
+            duration_secs INTEGER NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_utc INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS schedule_occurrences (
+            schedule_id TEXT NOT NULL,
+            occurrence_utc INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            session_id TEXT,
+            PRIMARY KEY (schedule_id, occurrence_utc)
+        );",
+    )
+}
+
+// v6: pomodoro plans and the intervals actually spent in each phase
+fn create_pomodoro_tables(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS pomodoro_sessions (
+            session_id TEXT PRIMARY KEY,
+            work_secs INTEGER NOT NULL,
+            short_break_secs INTEGER NOT NULL,
+            long_break_secs INTEGER NOT NULL,
+            work_intervals INTEGER NOT NULL,
+            long_break_every INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS session_intervals (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            phase_index INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            started_utc INTEGER NOT NULL,
+            ended_utc INTEGER,
+            completed INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX IF NOT EXISTS idx_session_intervals_session ON session_intervals (session_id, started_utc);",
+    )
+}
+
+// v7: named rule sets. Rules and sessions without a profile keep using the
+// default (unassigned) rule set, so existing data behaves as before.
+fn create_block_profiles(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS block_profiles (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            created_utc INTEGER NOT NULL
+        );
+        ALTER TABLE block_rules ADD COLUMN profile_id TEXT;
+        ALTER TABLE sessions ADD COLUMN profile_id TEXT;
+        CREATE INDEX IF NOT EXISTS idx_block_rules_profile ON block_rules (profile_id);",
+    )
+}
+
+// v8: profiles can allow their rules' apps and block everything else
+fn add_profile_list_mode(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "ALTER TABLE block_profiles ADD COLUMN list_mode TEXT NOT NULL DEFAULT 'blocklist';
+        ALTER TABLE block_profiles ADD COLUMN unlisted_mode TEXT NOT NULL DEFAULT 'hard';",
+    )
+}
+
+// v9: pids suspended by `freeze` rules, kept until their session ends
+fn create_frozen_processes(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS frozen_processes (
+            pid INTEGER PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            process_name TEXT NOT NULL,
+            frozen_utc INTEGER NOT NULL
+        );",
+    )
+}
+
+// v10: requests to end a session early and how each one was resolved
+fn create_early_end_requests(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS early_end_requests (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            reason TEXT,
+            requested_utc INTEGER NOT NULL,
+            unlocks_utc INTEGER NOT NULL,
+            resolved_utc INTEGER,
+            outcome TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_early_end_requests_session ON early_end_requests (session_id, requested_utc);",
+    )
+}
+
+// v11: sessions started from a schedule use its profile's rules
+fn add_schedule_profile(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch("ALTER TABLE session_schedules ADD COLUMN profile_id TEXT;")
+}
+
+// v12: repeated DNS queries for one name are stored as a single row
+fn add_block_event_count(tx: &Transaction) -> SqliteResult<()> {
+    tx.execute_batch("ALTER TABLE block_events ADD COLUMN count INTEGER NOT NULL DEFAULT 1;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A database as written by FocusLock before migrations existed
+    const FIXTURE_V0: &str = "
+        CREATE TABLE favorites (id TEXT PRIMARY KEY, app_id TEXT NOT NULL, display_name TEXT NOT NULL, pinned_order INTEGER, icon_hint TEXT);
+        CREATE TABLE block_rules (id TEXT PRIMARY KEY, app_id TEXT NOT NULL, match_kind TEXT NOT NULL, mode TEXT NOT NULL);
+        CREATE TABLE sessions (id TEXT PRIMARY KEY, start_utc INTEGER NOT NULL, end_utc INTEGER NOT NULL, status TEXT NOT NULL, duration_secs INTEGER NOT NULL, remaining_secs INTEGER);
+        CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO favorites VALUES ('f1', 'code.exe', 'VS Code', 1, NULL);
+        INSERT INTO block_rules VALUES ('r1', 'discord.exe', 'exe', 'hard');
+        INSERT INTO block_rules VALUES ('r2', 'steam.exe', ' EXE', 'Soft ');
+        INSERT INTO block_rules VALUES ('r3', 'slack.exe', 'process', 'hard');
+        INSERT INTO block_rules VALUES ('r4', 'spotify.exe', 'exe', 'medium');
+        INSERT INTO sessions VALUES ('s1', 1700000000, 1700001500, 'completed', 1500, 0);
+        INSERT INTO settings VALUES ('strictMode', 'true');
+    ";
+
+    fn temp_db_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("focuslock-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn migrates_v0_fixture_to_latest() {
+        let path = temp_db_path("focuslock.db");
+        Connection::open(&path).unwrap().execute_batch(FIXTURE_V0).unwrap();
+
+        let mut conn = Connection::open(&path).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), 0);
+        migrate(&mut conn, Some(&path)).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+
+        // Existing data survives
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM favorites"), 1);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM sessions"), 1);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM settings"), 1);
+
+        // Bad rules are normalized or quarantined
+        let (kind, mode): (String, String) = conn
+            .query_row("SELECT match_kind, mode FROM block_rules WHERE id = 'r2'", [], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert_eq!((kind.as_str(), mode.as_str()), ("exe", "soft"));
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM block_rules"), 2);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM invalid_block_rules WHERE id IN ('r3', 'r4')"), 2);
+
+        // Later tables exist
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM session_interruptions"), 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM block_events"), 0);
+
+        // The pre-migration copy still has the original rows
+        let backup = Connection::open(backup_path(&path, 0)).unwrap();
+        assert_eq!(user_version(&backup).unwrap(), 0);
+        assert_eq!(count(&backup, "SELECT COUNT(*) FROM block_rules"), 4);
+
+        // Running again is a no-op
+        migrate(&mut conn, Some(&path)).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn fresh_database_gets_every_table_and_no_backup() {
+        let path = temp_db_path("focuslock.db");
+        let mut conn = Connection::open(&path).unwrap();
+        migrate(&mut conn, Some(&path)).unwrap();
+
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+        assert!(!backup_path(&path, 0).exists());
+        for table in ["favorites", "block_rules", "sessions", "settings", "invalid_block_rules", "session_heartbeats", "session_schedules", "session_intervals", "block_profiles", "frozen_processes", "early_end_requests"] {
+            let sql = format!("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = '{}'", table);
+            assert_eq!(count(&conn, &sql), 1, "{}", table);
+        }
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn versions_are_ordered() {
+        for pair in MIGRATIONS.windows(2) {
+            assert_eq!(pair[1].version, pair[0].version + 1);
+        }
+    }
+}
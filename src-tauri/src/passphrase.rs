@@ -0,0 +1,310 @@
+// Optional admin passphrase for destructive commands
+//
+// The passphrase is stored as an Argon2 PHC string under a settings key that
+// get_settings/upsert_setting never expose. Once one is set, loosening
+// commands (deleting rules, turning strict mode off, ...) need an unlock
+// token from `verify_passphrase`. Tokens live only in memory and expire after
+// a few minutes; repeated wrong guesses lock verification out for longer and
+// longer, and that count is kept in settings so a restart doesn't reset it.
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rusqlite::{Connection, Result as SqliteResult};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::State;
+
+use crate::db::{self, DbState};
+use crate::error::{CommandResult, FocusLockError};
+use crate::session::now_utc;
+use crate::strict;
+
+pub const PASSPHRASE_HASH_KEY: &str = "adminPassphraseHash";
+const FAILURES_KEY: &str = "adminPassphraseFailures";
+const LOCKED_UNTIL_KEY: &str = "adminPassphraseLockedUntil";
+
+// Settings only this module writes; get_settings/upsert_setting skip them
+pub const RESERVED_KEYS: [&str; 3] = [PASSPHRASE_HASH_KEY, FAILURES_KEY, LOCKED_UNTIL_KEY];
+
+const TOKEN_TTL_SECS: i64 = 5 * 60;
+const MIN_PASSPHRASE_CHARS: usize = 6;
+// Wrong guesses allowed before lockouts start
+const FREE_ATTEMPTS: u32 = 5;
+const FIRST_LOCKOUT_SECS: i64 = 30;
+const MAX_LOCKOUT_SECS: i64 = 15 * 60;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockToken {
+    pub token: String,
+    pub expires_utc: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PassphraseStatus {
+    pub enabled: bool,
+    pub locked_until_utc: Option<i64>,
+}
+
+// Failed verification attempts; doubles the lockout for every failure past
+// the free ones
+#[derive(Debug, Default)]
+pub struct Attempts {
+    failures: u32,
+    locked_until: i64,
+}
+
+impl Attempts {
+    pub fn load(conn: &Connection) -> SqliteResult<Self> {
+        let number = |key| -> SqliteResult<Option<i64>> { Ok(db::get_setting(conn, key)?.and_then(|v| v.parse().ok())) };
+        Ok(Attempts {
+            failures: number(FAILURES_KEY)?.map_or(0, |n| n.clamp(0, u32::MAX as i64) as u32),
+            locked_until: number(LOCKED_UNTIL_KEY)?.unwrap_or(0),
+        })
+    }
+
+    pub fn save(&self, conn: &Connection) -> SqliteResult<()> {
+        if self.failures == 0 {
+            conn.execute("DELETE FROM settings WHERE key IN (?1, ?2)", [FAILURES_KEY, LOCKED_UNTIL_KEY])?;
+            return Ok(());
+        }
+        for (key, value) in [(FAILURES_KEY, self.failures as i64), (LOCKED_UNTIL_KEY, self.locked_until)] {
+            conn.execute(
+                "INSERT INTO settings (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value = ?2",
+                (key, value.to_string()),
+            )?;
+        }
+        Ok(())
+    }
+
+    // Seconds until another attempt is allowed, if any
+    pub fn locked_for(&self, now: i64) -> Option<i64> {
+        (self.locked_until > now).then(|| self.locked_until - now)
+    }
+
+    pub fn record_failure(&mut self, now: i64) {
+        self.failures += 1;
+        if self.failures >= FREE_ATTEMPTS {
+            let doublings = (self.failures - FREE_ATTEMPTS).min(10);
+            self.locked_until = now + (FIRST_LOCKOUT_SECS << doublings).min(MAX_LOCKOUT_SECS);
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        *self = Attempts::default();
+    }
+}
+
+#[derive(Default)]
+pub struct PassphraseState {
+    tokens: Mutex<HashMap<String, i64>>,
+}
+
+fn stored_hash(conn: &Connection) -> SqliteResult<Option<String>> {
+    db::get_setting(conn, PASSPHRASE_HASH_KEY)
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn hash_passphrase(passphrase: &str) -> CommandResult<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| FocusLockError::Internal(format!("Failed to hash passphrase: {}", e)))
+}
+
+pub fn passphrase_matches(passphrase: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(passphrase.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+impl PassphraseState {
+    pub fn new() -> Self {
+        PassphraseState::default()
+    }
+
+    // Passes when no passphrase is set or `token` is a live unlock token
+    pub fn ensure_authorized(&self, conn: &Connection, token: Option<&str>, action: &str) -> CommandResult<()> {
+        if stored_hash(conn)?.is_none() {
+            return Ok(());
+        }
+
+        let now = now_utc();
+        let mut tokens = self.tokens.lock()?;
+        tokens.retain(|_, expires| *expires > now);
+
+        if token.is_some_and(|t| tokens.contains_key(t)) {
+            return Ok(());
+        }
+        Err(FocusLockError::PassphraseRequired {
+            action: action.to_string(),
+        })
+    }
+
+    fn revoke_all(&self) -> CommandResult<()> {
+        self.tokens.lock()?.clear();
+        Ok(())
+    }
+}
+
+// Tauri Commands - Passphrase
+#[tauri::command]
+pub fn get_passphrase_status(db: State<DbState>) -> CommandResult<PassphraseStatus> {
+    let conn = db.conn.lock()?;
+    let enabled = stored_hash(&conn)?.is_some();
+    let now = now_utc();
+    let locked_until_utc = Attempts::load(&conn)?.locked_for(now).map(|secs| now + secs);
+    Ok(PassphraseStatus { enabled, locked_until_utc })
+}
+
+#[tauri::command]
+pub fn verify_passphrase(
+    db: State<DbState>,
+    state: State<PassphraseState>,
+    passphrase: String,
+) -> CommandResult<UnlockToken> {
+    let now = now_utc();
+    let hash = {
+        let conn = db.conn.lock()?;
+        if let Some(retry_after_secs) = Attempts::load(&conn)?.locked_for(now) {
+            return Err(FocusLockError::RateLimited { retry_after_secs });
+        }
+        stored_hash(&conn)?
+    };
+    let Some(hash) = hash else {
+        return Err(FocusLockError::InvalidState("No passphrase is set".to_string()));
+    };
+
+    // Checked without holding the database lock; Argon2 is slow on purpose
+    let matches = passphrase_matches(&passphrase, &hash);
+    let conn = db.conn.lock()?;
+    let mut attempts = Attempts::load(&conn)?;
+    if !matches {
+        attempts.record_failure(now);
+        attempts.save(&conn)?;
+        return Err(FocusLockError::validation("passphrase", "", "Incorrect passphrase"));
+    }
+    attempts.record_success();
+    attempts.save(&conn)?;
+
+    let token = UnlockToken {
+        token: new_token(),
+        expires_utc: now + TOKEN_TTL_SECS,
+    };
+    state.tokens.lock()?.insert(token.token.clone(), token.expires_utc);
+    Ok(token)
+}
+
+// Sets, changes or (with `new_passphrase: None`) removes the passphrase.
+// Changing an existing one needs an unlock token; every old token is revoked.
+#[tauri::command]
+pub fn set_passphrase(
+    db: State<DbState>,
+    state: State<PassphraseState>,
+    new_passphrase: Option<String>,
+    unlock_token: Option<String>,
+) -> CommandResult<PassphraseStatus> {
+    if let Some(new) = &new_passphrase {
+        if new.chars().count() < MIN_PASSPHRASE_CHARS {
+            return Err(FocusLockError::validation(
+                "newPassphrase",
+                "",
+                format!("Passphrase must be at least {} characters", MIN_PASSPHRASE_CHARS),
+            ));
+        }
+    }
+
+    let hash = new_passphrase.as_deref().map(hash_passphrase).transpose()?;
+
+    let conn = db.conn.lock()?;
+    state.ensure_authorized(&conn, unlock_token.as_deref(), "change the passphrase")?;
+
+    match hash {
+        Some(hash) => {
+            conn.execute(
+                "INSERT INTO settings (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value = ?2",
+                (PASSPHRASE_HASH_KEY, &hash),
+            )?;
+        }
+        None => {
+            strict::ensure_unlocked(&conn, "remove the passphrase")?;
+            conn.execute("DELETE FROM settings WHERE key = ?1", [PASSPHRASE_HASH_KEY])?;
+        }
+    }
+    state.revoke_all()?;
+
+    Ok(PassphraseStatus {
+        enabled: new_passphrase.is_some(),
+        locked_until_utc: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lockouts_start_after_free_attempts_and_double() {
+        let mut attempts = Attempts::default();
+        for _ in 0..FREE_ATTEMPTS - 1 {
+            attempts.record_failure(100);
+        }
+        assert_eq!(attempts.locked_for(100), None);
+
+        attempts.record_failure(100);
+        assert_eq!(attempts.locked_for(100), Some(30));
+        attempts.record_failure(130);
+        assert_eq!(attempts.locked_for(130), Some(60));
+
+        for _ in 0..20 {
+            attempts.record_failure(200);
+        }
+        assert_eq!(attempts.locked_for(200), Some(MAX_LOCKOUT_SECS));
+
+        attempts.record_success();
+        assert_eq!(attempts.locked_for(200), None);
+    }
+
+    #[test]
+    fn lockouts_survive_a_restart() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+
+        let mut attempts = Attempts::load(&conn).unwrap();
+        for _ in 0..FREE_ATTEMPTS {
+            attempts.record_failure(100);
+            attempts.save(&conn).unwrap();
+        }
+
+        // A fresh load is what the next process sees
+        let mut reloaded = Attempts::load(&conn).unwrap();
+        assert_eq!(reloaded.locked_for(100), Some(FIRST_LOCKOUT_SECS));
+        reloaded.record_failure(130);
+        assert_eq!(reloaded.locked_for(130), Some(2 * FIRST_LOCKOUT_SECS));
+
+        reloaded.record_success();
+        reloaded.save(&conn).unwrap();
+        assert_eq!(Attempts::load(&conn).unwrap().locked_for(130), None);
+        assert_eq!(db::get_setting(&conn, FAILURES_KEY).unwrap(), None);
+    }
+
+    #[test]
+    fn hashes_verify_only_the_same_passphrase() {
+        let hash = hash_passphrase("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2"));
+        assert!(passphrase_matches("correct horse", &hash));
+        assert!(!passphrase_matches("correct horse ", &hash));
+        assert!(!passphrase_matches("correct horse", "not a hash"));
+    }
+}
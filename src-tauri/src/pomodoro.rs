@@ -0,0 +1,474 @@
+// Pomodoro sessions: N work intervals separated by short and long breaks
+//
+// A pomodoro is an ordinary session whose duration is the whole plan. The
+// current phase is derived from how much of the session has elapsed, so
+// pausing and resuming shift the plan along with the countdown. The session
+// ticker calls `advance` every second; it keeps one open row in
+// `session_intervals` for the current phase and emits `pomodoro-phase` when
+// the phase changes. Block rules are only enforced during work phases.
+
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, State};
+use uuid::Uuid;
+
+use crate::db::{self, DbState, Session, SessionStatus};
+use crate::error::{CommandResult, FocusLockError, OrNotFound};
+use crate::passphrase::PassphraseState;
+use crate::session::{self, now_utc};
+
+pub const PHASE_EVENT: &str = "pomodoro-phase";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PhaseKind {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PhaseKind {
+    pub const ALL: [PhaseKind; 3] = [PhaseKind::Work, PhaseKind::ShortBreak, PhaseKind::LongBreak];
+
+    // Stored in session_intervals.kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PhaseKind::Work => "work",
+            PhaseKind::ShortBreak => "short_break",
+            PhaseKind::LongBreak => "long_break",
+        }
+    }
+}
+
+db::text_enum!(PhaseKind);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PomodoroConfig {
+    pub work_secs: i32,
+    pub short_break_secs: i32,
+    pub long_break_secs: i32,
+    pub work_intervals: i32,
+    // A long break replaces every Nth short break
+    pub long_break_every: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Phase {
+    pub index: usize,
+    pub kind: PhaseKind,
+    // 1-based number of the work interval this phase is or follows
+    pub work_interval: i32,
+    pub secs: i64,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PomodoroStatus {
+    pub session_id: String,
+    pub phase: PhaseKind,
+    pub phase_index: usize,
+    pub work_interval: i32,
+    pub work_intervals: i32,
+    // Work intervals fully finished so far
+    pub completed_work_intervals: i32,
+    pub phase_remaining_secs: i64,
+    pub phase_ends_utc: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInterval {
+    pub id: String,
+    pub session_id: String,
+    pub phase_index: i64,
+    pub kind: PhaseKind,
+    pub started_utc: i64,
+    pub ended_utc: Option<i64>,
+    pub completed: bool,
+}
+
+impl PomodoroConfig {
+    pub fn validate(&self) -> CommandResult<()> {
+        let checks = [
+            ("workSecs", self.work_secs, 60, 4 * 60 * 60),
+            ("shortBreakSecs", self.short_break_secs, 0, 60 * 60),
+            ("longBreakSecs", self.long_break_secs, 0, 2 * 60 * 60),
+            ("workIntervals", self.work_intervals, 1, 16),
+            ("longBreakEvery", self.long_break_every, 1, 16),
+        ];
+
+        for (field, value, min, max) in checks {
+            if !(min..=max).contains(&value) {
+                return Err(FocusLockError::validation(
+                    field,
+                    &value.to_string(),
+                    format!("{} must be between {} and {}", field, min, max),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Work, break, work, ... ending on a work interval
+    pub fn phases(&self) -> Vec<Phase> {
+        let mut phases = Vec::new();
+
+        for n in 1..=self.work_intervals {
+            phases.push(Phase {
+                index: phases.len(),
+                kind: PhaseKind::Work,
+                work_interval: n,
+                secs: self.work_secs as i64,
+            });
+
+            if n == self.work_intervals {
+                break;
+            }
+
+            let (kind, secs) = if n % self.long_break_every == 0 {
+                (PhaseKind::LongBreak, self.long_break_secs)
+            } else {
+                (PhaseKind::ShortBreak, self.short_break_secs)
+            };
+            // Zero-length breaks are allowed and simply don't exist
+            if secs > 0 {
+                phases.push(Phase {
+                    index: phases.len(),
+                    kind,
+                    work_interval: n,
+                    secs: secs as i64,
+                });
+            }
+        }
+
+        phases
+    }
+
+    pub fn total_secs(&self) -> i64 {
+        self.phases().iter().map(|p| p.secs).sum()
+    }
+
+    // The phase `elapsed` seconds into the plan and the seconds left in it
+    pub fn phase_at(&self, elapsed: i64) -> Option<(Phase, i64)> {
+        let mut start = 0;
+        for phase in self.phases() {
+            if elapsed < start + phase.secs {
+                return Some((phase, start + phase.secs - elapsed));
+            }
+            start += phase.secs;
+        }
+        None
+    }
+}
+
+pub fn find_config(conn: &Connection, session_id: &str) -> SqliteResult<Option<PomodoroConfig>> {
+    conn.query_row(
+        "SELECT work_secs, short_break_secs, long_break_secs, work_intervals, long_break_every
+         FROM pomodoro_sessions WHERE session_id = ?1",
+        [session_id],
+        |row| {
+            Ok(PomodoroConfig {
+                work_secs: row.get(0)?,
+                short_break_secs: row.get(1)?,
+                long_break_secs: row.get(2)?,
+                work_intervals: row.get(3)?,
+                long_break_every: row.get(4)?,
+            })
+        },
+    )
+    .optional()
+}
+
+fn status_at(config: &PomodoroConfig, session: &Session, now: i64) -> Option<PomodoroStatus> {
+    let elapsed = session.duration_secs as i64 - session::remaining_secs(session, now);
+    let (phase, phase_remaining_secs) = config.phase_at(elapsed)?;
+
+    Some(PomodoroStatus {
+        session_id: session.id.clone(),
+        phase: phase.kind,
+        phase_index: phase.index,
+        work_interval: phase.work_interval,
+        work_intervals: config.work_intervals,
+        completed_work_intervals: match phase.kind {
+            PhaseKind::Work => phase.work_interval - 1,
+            _ => phase.work_interval,
+        },
+        phase_remaining_secs,
+        phase_ends_utc: now + phase_remaining_secs,
+    })
+}
+
+// False while a pomodoro session is on a break; the monitor skips enforcement
+pub fn enforcing(db: &DbState, session: &Session, now: i64) -> bool {
+    let Ok(conn) = db.conn.lock() else {
+        return true;
+    };
+    enforcing_with(&conn, session, now)
+}
+
+// Same check for callers already holding the connection
+pub fn enforcing_with(conn: &Connection, session: &Session, now: i64) -> bool {
+    match find_config(conn, &session.id) {
+        Ok(Some(config)) => status_at(&config, session, now).is_none_or(|s| s.phase == PhaseKind::Work),
+        _ => true,
+    }
+}
+
+// Ends the session's open interval, if any
+pub fn close_open_interval(conn: &Connection, session_id: &str, now: i64, completed: bool) -> SqliteResult<()> {
+    conn.execute(
+        "UPDATE session_intervals SET ended_utc = MAX(started_utc, ?2), completed = ?3
+         WHERE session_id = ?1 AND ended_utc IS NULL",
+        (session_id, now, completed),
+    )?;
+    Ok(())
+}
+
+// Brings the session's intervals in line with its current phase. Only called
+// for running sessions; returns the new status when an interval was opened so
+// the caller can emit it.
+pub fn advance(conn: &Connection, session: &Session, now: i64) -> SqliteResult<Option<PomodoroStatus>> {
+    let Some(config) = find_config(conn, &session.id)? else {
+        return Ok(None);
+    };
+    let current = status_at(&config, session, now);
+
+    let open: Option<i64> = conn
+        .query_row(
+            "SELECT phase_index FROM session_intervals WHERE session_id = ?1 AND ended_utc IS NULL",
+            [&session.id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    let current_index = current.as_ref().map(|s| s.phase_index as i64);
+    if open.is_some() && open == current_index {
+        return Ok(None);
+    }
+
+    // Moving past a phase means it ran to the end
+    if let Some(open) = open {
+        let finished = current_index.is_none_or(|c| c > open);
+        close_open_interval(conn, &session.id, now, finished)?;
+    }
+
+    let Some(status) = current else {
+        return Ok(None);
+    };
+
+    conn.execute(
+        "INSERT INTO session_intervals (id, session_id, phase_index, kind, started_utc) VALUES (?1, ?2, ?3, ?4, ?5)",
+        (
+            Uuid::new_v4().to_string(),
+            &session.id,
+            status.phase_index as i64,
+            status.phase.as_str(),
+            now,
+        ),
+    )?;
+
+    Ok(Some(status))
+}
+
+// Total seconds spent in work intervals, per session
+pub fn work_secs_by_session(conn: &Connection, now: i64) -> SqliteResult<HashMap<String, i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT session_id, SUM(COALESCE(ended_utc, ?1) - started_utc) FROM session_intervals
+         WHERE kind = 'work' GROUP BY session_id",
+    )?;
+    let totals = stmt
+        .query_map([now], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<SqliteResult<_>>()?;
+    Ok(totals)
+}
+
+// Tauri Commands - Pomodoro
+#[tauri::command]
+pub fn start_pomodoro_session(
+    app: AppHandle,
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    config: PomodoroConfig,
+    profile_id: Option<String>,
+) -> CommandResult<Session> {
+    config.validate()?;
+
+    let (session, phase) = {
+        let conn = db.conn.lock()?;
+        crate::profiles::ensure_exists(&conn, profile_id.as_deref())?;
+        let now = now_utc();
+        let total = config.total_secs();
+
+        let session = Session {
+            id: Uuid::new_v4().to_string(),
+            start_utc: now,
+            end_utc: now + total,
+            status: SessionStatus::Scheduled,
+            duration_secs: total as i32,
+            remaining_secs: None,
+            profile_id,
+        };
+        db::insert_session(&conn, &session)?;
+        conn.execute(
+            "INSERT INTO pomodoro_sessions (session_id, work_secs, short_break_secs, long_break_secs, work_intervals, long_break_every)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (
+                &session.id,
+                config.work_secs,
+                config.short_break_secs,
+                config.long_break_secs,
+                config.work_intervals,
+                config.long_break_every,
+            ),
+        )?;
+
+        // Started under the same lock so the scheduler can't start another
+        // session in between; the same path as any other session from here on
+        session::apply_with_phase(&conn, &passphrase, None, &session.id, SessionStatus::Running)?
+    };
+
+    session::emit_started(&app, &session);
+    // The first work interval opens here, not on a tick
+    if let Some(phase) = phase {
+        let _ = app.emit(PHASE_EVENT, phase);
+    }
+    Ok(session)
+}
+
+#[tauri::command]
+pub fn get_pomodoro_status(db: State<DbState>, session_id: String) -> CommandResult<Option<PomodoroStatus>> {
+    let conn = db.conn.lock()?;
+    let session = db::find_session(&conn, &session_id).or_not_found("session", &session_id)?;
+    let Some(config) = find_config(&conn, &session_id)? else {
+        return Ok(None);
+    };
+    Ok(status_at(&config, &session, now_utc()))
+}
+
+#[tauri::command]
+pub fn get_session_intervals(db: State<DbState>, session_id: String) -> CommandResult<Vec<SessionInterval>> {
+    let conn = db.conn.lock()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, session_id, phase_index, kind, started_utc, ended_utc, completed
+         FROM session_intervals WHERE session_id = ?1 ORDER BY started_utc",
+    )?;
+
+    let intervals = stmt
+        .query_map([&session_id], |row| {
+            Ok(SessionInterval {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                phase_index: row.get(2)?,
+                kind: row.get(3)?,
+                started_utc: row.get(4)?,
+                ended_utc: row.get(5)?,
+                completed: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(intervals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classic() -> PomodoroConfig {
+        PomodoroConfig {
+            work_secs: 25 * 60,
+            short_break_secs: 5 * 60,
+            long_break_secs: 15 * 60,
+            work_intervals: 4,
+            long_break_every: 2,
+        }
+    }
+
+    #[test]
+    fn plan_alternates_work_and_breaks() {
+        let kinds: Vec<PhaseKind> = classic().phases().iter().map(|p| p.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                PhaseKind::Work,
+                PhaseKind::ShortBreak,
+                PhaseKind::Work,
+                PhaseKind::LongBreak,
+                PhaseKind::Work,
+                PhaseKind::ShortBreak,
+                PhaseKind::Work,
+            ]
+        );
+        assert_eq!(classic().total_secs(), (4 * 25 + 2 * 5 + 15) * 60);
+    }
+
+    #[test]
+    fn phase_at_tracks_elapsed_time() {
+        let config = classic();
+
+        let (phase, left) = config.phase_at(0).unwrap();
+        assert_eq!((phase.kind, phase.work_interval, left), (PhaseKind::Work, 1, 25 * 60));
+
+        let (phase, left) = config.phase_at(26 * 60).unwrap();
+        assert_eq!((phase.kind, left), (PhaseKind::ShortBreak, 4 * 60));
+
+        let (phase, _) = config.phase_at(56 * 60).unwrap();
+        assert_eq!((phase.kind, phase.work_interval), (PhaseKind::LongBreak, 2));
+
+        assert!(config.phase_at(config.total_secs()).is_none());
+    }
+
+    #[test]
+    fn config_is_validated() {
+        assert!(classic().validate().is_ok());
+        assert!(PomodoroConfig { work_secs: 0, ..classic() }.validate().is_err());
+        assert!(PomodoroConfig { work_intervals: 0, ..classic() }.validate().is_err());
+
+        // Zero-length breaks drop out of the plan
+        let no_breaks = PomodoroConfig { short_break_secs: 0, long_break_secs: 0, ..classic() };
+        assert!(no_breaks.phases().iter().all(|p| p.kind == PhaseKind::Work));
+    }
+
+    #[test]
+    fn phase_kinds_parse_strictly() {
+        assert_eq!("short_break".parse::<PhaseKind>(), Ok(PhaseKind::ShortBreak));
+        assert!("shortBreak".parse::<PhaseKind>().is_err());
+        assert!("".parse::<PhaseKind>().is_err());
+    }
+
+    #[test]
+    fn starting_and_resuming_return_the_opened_phase() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+        let passphrase = PassphraseState::new();
+
+        let now = now_utc();
+        let config = classic();
+        let session = Session {
+            id: "p1".to_string(),
+            start_utc: now,
+            end_utc: now + config.total_secs(),
+            status: SessionStatus::Scheduled,
+            duration_secs: config.total_secs() as i32,
+            remaining_secs: None,
+            profile_id: None,
+        };
+        db::insert_session(&conn, &session).unwrap();
+        conn.execute(
+            "INSERT INTO pomodoro_sessions (session_id, work_secs, short_break_secs, long_break_secs, work_intervals, long_break_every)
+             VALUES ('p1', ?1, ?2, ?3, ?4, ?5)",
+            (config.work_secs, config.short_break_secs, config.long_break_secs, config.work_intervals, config.long_break_every),
+        )
+        .unwrap();
+
+        let (_, phase) = session::apply_with_phase(&conn, &passphrase, None, "p1", SessionStatus::Running).unwrap();
+        let phase = phase.expect("the first work interval is reported");
+        assert_eq!((phase.phase, phase.phase_index), (PhaseKind::Work, 0));
+
+        session::apply(&conn, &passphrase, None, "p1", SessionStatus::Paused).unwrap();
+        let (_, phase) = session::apply_with_phase(&conn, &passphrase, None, "p1", SessionStatus::Running).unwrap();
+        assert_eq!(phase.map(|p| p.phase), Some(PhaseKind::Work));
+    }
+}
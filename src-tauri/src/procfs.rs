@@ -0,0 +1,199 @@
+// Linux process enumeration and control backed by /proc
+//
+// Only processes owned by the current user are ever returned, so the monitor
+// can't touch system daemons or other users' sessions.
+
+use std::collections::HashSet;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+
+#[derive(Debug, Clone)]
+pub struct ProcEntry {
+    pub pid: i32,
+    pub comm: String,
+    pub exe_path: Option<String>,
+    pub cmdline: Vec<String>,
+    pub uid: u32,
+    // Controlling terminal from /proc/<pid>/stat; 0 when there is none
+    pub tty_nr: i32,
+    // DISPLAY or WAYLAND_DISPLAY is set in the process environment
+    pub has_display: bool,
+}
+
+impl ProcEntry {
+    // `comm` is truncated to 15 bytes by the kernel, so prefer the basename of
+    // the exe link when we can read it
+    pub fn name(&self) -> String {
+        self.exe_path
+            .as_deref()
+            .and_then(|p| p.rsplit('/').next())
+            .filter(|n| !n.is_empty())
+            .map(|n| n.trim_end_matches(" (deleted)").to_string())
+            .unwrap_or_else(|| self.comm.clone())
+    }
+
+    // Best guess at "an app with windows": it can reach a display server,
+    // wasn't started from a terminal (shells and CLI tools have a tty), and is
+    // a program some .desktop entry launches. The last check keeps out the
+    // user services a desktop session starts, which have a display and no
+    // tty too. `desktop_programs` holds lowercased program file names.
+    pub fn is_gui(&self, desktop_programs: &HashSet<String>) -> bool {
+        if !self.has_display || self.tty_nr != 0 {
+            return false;
+        }
+
+        // argv[0] covers scripts and AppImages whose exe is an interpreter
+        let argv0 = self.cmdline.first().and_then(|arg| arg.rsplit('/').next());
+        [Some(self.name().as_str()), argv0]
+            .into_iter()
+            .flatten()
+            .any(|name| desktop_programs.contains(&name.to_lowercase()))
+    }
+}
+
+// Field 7 of /proc/<pid>/stat. `comm` (field 2) may contain spaces and
+// parentheses, so count from the last ')'.
+fn parse_tty_nr(stat: &str) -> Option<i32> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(4)?.parse().ok()
+}
+
+pub fn current_uid() -> u32 {
+    unsafe { libc::geteuid() }
+}
+
+fn read_entry(pid: i32) -> Option<ProcEntry> {
+    let base = format!("/proc/{}", pid);
+    let uid = fs::metadata(&base).ok()?.uid();
+
+    let comm = fs::read_to_string(format!("{}/comm", base))
+        .ok()?
+        .trim_end()
+        .to_string();
+
+    let exe_path = fs::read_link(format!("{}/exe", base))
+        .ok()
+        .and_then(|p| p.to_str().map(String::from));
+
+    let cmdline: Vec<String> = fs::read(format!("{}/cmdline", base))
+        .unwrap_or_default()
+        .split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).to_string())
+        .collect();
+
+    // Kernel threads have neither an exe link nor a command line
+    if exe_path.is_none() && cmdline.is_empty() {
+        return None;
+    }
+
+    let tty_nr = fs::read_to_string(format!("{}/stat", base))
+        .ok()
+        .and_then(|stat| parse_tty_nr(&stat))
+        .unwrap_or(0);
+
+    let has_display = fs::read(format!("{}/environ", base))
+        .unwrap_or_default()
+        .split(|b| *b == 0)
+        .any(|var| var.starts_with(b"DISPLAY=") || var.starts_with(b"WAYLAND_DISPLAY="));
+
+    Some(ProcEntry { pid, comm, exe_path, cmdline, uid, tty_nr, has_display })
+}
+
+// Walk /proc/<pid> and return every process owned by the current user,
+// excluding FocusLock itself
+pub fn list_user_processes() -> Vec<ProcEntry> {
+    let uid = current_uid();
+    let own_pid = std::process::id() as i32;
+
+    let Ok(dir) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    dir.filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<i32>().ok())
+        .filter(|pid| *pid != own_pid)
+        .filter_map(read_entry)
+        .filter(|p| p.uid == uid)
+        .collect()
+}
+
+pub fn kill(pid: i32) -> bool {
+    send_signal(pid, libc::SIGKILL)
+}
+
+pub fn stop(pid: i32) -> bool {
+    send_signal(pid, libc::SIGSTOP)
+}
+
+pub fn cont(pid: i32) -> bool {
+    send_signal(pid, libc::SIGCONT)
+}
+
+fn send_signal(pid: i32, signal: libc::c_int) -> bool {
+    // Re-check ownership right before signalling in case the pid was recycled
+    match fs::metadata(format!("/proc/{}", pid)) {
+        Ok(meta) if meta.uid() == current_uid() => {}
+        _ => return false,
+    }
+    unsafe { libc::kill(pid, signal) == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(pid: i32) -> char {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+        stat.rsplit_once(')').unwrap().1.trim_start().chars().next().unwrap()
+    }
+
+    fn entry(name: &str, tty_nr: i32, has_display: bool) -> ProcEntry {
+        ProcEntry {
+            pid: 1,
+            comm: name.to_string(),
+            exe_path: Some(format!("/usr/bin/{}", name)),
+            cmdline: vec![name.to_string()],
+            uid: 1000,
+            tty_nr,
+            has_display,
+        }
+    }
+
+    #[test]
+    fn gui_apps_need_a_display_no_tty_and_a_desktop_entry() {
+        assert_eq!(parse_tty_nr("1234 (bash) S 1 1234 1234 34816 1234 4194560"), Some(34816));
+        assert_eq!(parse_tty_nr("99 (Web Content (x)) S 1 99 99 0 -1 4194560"), Some(0));
+        assert_eq!(parse_tty_nr("99 (truncated"), None);
+
+        let programs: HashSet<String> = ["firefox", "obsidian.appimage"].iter().map(|p| p.to_string()).collect();
+        assert!(entry("firefox", 0, true).is_gui(&programs));
+        assert!(!entry("firefox", 34816, true).is_gui(&programs));
+        assert!(!entry("firefox", 0, false).is_gui(&programs));
+
+        // Session services look like apps apart from having no desktop entry
+        assert!(!entry("gjs", 0, true).is_gui(&programs));
+        assert!(!entry("dconf-service", 0, true).is_gui(&programs));
+
+        let mut appimage = entry("ld-linux-x86-64.so.2", 0, true);
+        appimage.cmdline = vec!["/home/me/Apps/Obsidian.AppImage".to_string()];
+        assert!(appimage.is_gui(&programs));
+    }
+
+    #[test]
+    fn stop_and_cont_suspend_a_child() {
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id() as i32;
+
+        assert!(stop(pid));
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(state(pid), 'T');
+
+        assert!(cont(pid));
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert_ne!(state(pid), 'T');
+
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
@@ -0,0 +1,334 @@
+// Block profiles: named rule sets ("Deep work", "Writing", ...)
+//
+// Each rule belongs to at most one profile. A session with a profile is
+// enforced with that profile's rules only; rules and sessions without one
+// make up the default set, which is how everything behaved before profiles.
+//
+// An allowlist profile inverts its rules: they name the apps that may run,
+// and every other GUI app outside the protected list is blocked with the
+// profile's `unlisted_mode`.
+
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::db::{self, BlockMode, DbState, ListMode, SessionStatus};
+use crate::enforcement::{Policy, ProtectedList, RuleMatcher};
+use crate::error::{CommandResult, FocusLockError, OrNotFound};
+use crate::passphrase::PassphraseState;
+use crate::session::now_utc;
+use crate::strict;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockProfile {
+    pub id: String,
+    pub name: String,
+    pub created_utc: i64,
+    pub rule_count: i64,
+    pub list_mode: ListMode,
+    // What happens to apps an allowlist doesn't mention
+    pub unlisted_mode: BlockMode,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsertBlockProfile {
+    pub name: String,
+    pub list_mode: Option<String>,
+    pub unlisted_mode: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateBlockProfile {
+    pub name: Option<String>,
+    pub list_mode: Option<String>,
+    pub unlisted_mode: Option<String>,
+}
+
+// "true" exempts terminal emulators from allowlist mode
+pub const ALLOWLIST_TERMINALS_KEY: &str = "allowlistTerminals";
+
+const PROFILE_QUERY: &str = "SELECT p.id, p.name, p.created_utc,
+        (SELECT COUNT(*) FROM block_rules r WHERE r.profile_id = p.id),
+        p.list_mode, p.unlisted_mode
+    FROM block_profiles p";
+
+fn profile_from_row(row: &rusqlite::Row) -> SqliteResult<BlockProfile> {
+    Ok(BlockProfile {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_utc: row.get(2)?,
+        rule_count: row.get(3)?,
+        list_mode: row.get(4)?,
+        unlisted_mode: row.get(5)?,
+    })
+}
+
+fn find_profile(conn: &Connection, id: &str) -> CommandResult<BlockProfile> {
+    conn.query_row(&format!("{} WHERE p.id = ?1", PROFILE_QUERY), [id], profile_from_row)
+        .or_not_found("block profile", id)
+}
+
+// Rules and sessions may only point at profiles that exist
+pub fn ensure_exists(conn: &Connection, profile_id: Option<&str>) -> CommandResult<()> {
+    if let Some(id) = profile_id {
+        find_profile(conn, id)?;
+    }
+    Ok(())
+}
+
+// How the rules of `profile_id` are applied; the default set is a blocklist
+pub fn list_mode(conn: &Connection, profile_id: Option<&str>) -> SqliteResult<ListMode> {
+    let Some(id) = profile_id else {
+        return Ok(ListMode::Blocklist);
+    };
+    let mode = conn
+        .query_row("SELECT list_mode FROM block_profiles WHERE id = ?1", [id], |row| row.get(0))
+        .optional()?;
+    Ok(mode.unwrap_or(ListMode::Blocklist))
+}
+
+// What the monitor enforces for a session using `profile_id`
+pub fn policy_for(conn: &Connection, profile_id: Option<&str>) -> SqliteResult<Policy> {
+    let matcher = RuleMatcher::new(db::find_block_rules(conn, profile_id)?);
+
+    let profile = match profile_id {
+        Some(id) => conn
+            .query_row(&format!("{} WHERE p.id = ?1", PROFILE_QUERY), [id], profile_from_row)
+            .optional()?,
+        None => None,
+    };
+
+    match profile {
+        Some(profile) if profile.list_mode == ListMode::Allowlist => {
+            let terminals = db::get_setting(conn, ALLOWLIST_TERMINALS_KEY)?;
+            Ok(Policy::Allowlist {
+                allowed: matcher,
+                mode: profile.unlisted_mode,
+                protected: ProtectedList::new(terminals.as_deref() == Some("true")),
+            })
+        }
+        _ => Ok(Policy::Blocklist(matcher)),
+    }
+}
+
+// Trimmed, non-empty and not used by another profile
+fn checked_name(conn: &Connection, name: &str, id: Option<&str>) -> CommandResult<String> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return Err(FocusLockError::validation("name", name, "Name cannot be empty"));
+    }
+
+    let taken: Option<String> = conn
+        .query_row(
+            "SELECT id FROM block_profiles WHERE name = ?1 COLLATE NOCASE",
+            [trimmed],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if taken.is_some_and(|other| Some(other.as_str()) != id) {
+        return Err(FocusLockError::validation(
+            "name",
+            name,
+            format!("A profile named '{}' already exists", trimmed),
+        ));
+    }
+
+    Ok(trimmed.to_string())
+}
+
+// Tauri Commands - Block profiles
+#[tauri::command]
+pub fn get_block_profiles(db: State<DbState>) -> CommandResult<Vec<BlockProfile>> {
+    let conn = db.conn.lock()?;
+    let mut stmt = conn.prepare(&format!("{} ORDER BY p.name COLLATE NOCASE", PROFILE_QUERY))?;
+
+    let profiles = stmt
+        .query_map([], profile_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(profiles)
+}
+
+#[tauri::command]
+pub fn create_block_profile(db: State<DbState>, profile: InsertBlockProfile) -> CommandResult<BlockProfile> {
+    let list_mode = match &profile.list_mode {
+        Some(value) => db::parse_rule_field("listMode", value)?,
+        None => ListMode::Blocklist,
+    };
+    let unlisted_mode = match &profile.unlisted_mode {
+        Some(value) => db::parse_block_mode("unlistedMode", value)?,
+        None => BlockMode::Hard,
+    };
+
+    let conn = db.conn.lock()?;
+    let profile = BlockProfile {
+        id: Uuid::new_v4().to_string(),
+        name: checked_name(&conn, &profile.name, None)?,
+        created_utc: now_utc(),
+        rule_count: 0,
+        list_mode,
+        unlisted_mode,
+    };
+
+    conn.execute(
+        "INSERT INTO block_profiles (id, name, created_utc, list_mode, unlisted_mode) VALUES (?1, ?2, ?3, ?4, ?5)",
+        (&profile.id, &profile.name, profile.created_utc, &profile.list_mode, &profile.unlisted_mode),
+    )?;
+
+    Ok(profile)
+}
+
+// Leaving allowlist mode or only warning about unlisted apps blocks less. On
+// the profile a session is using, entering allowlist mode turns what was
+// blocked into the only thing allowed, so any list mode change counts.
+fn profile_loosened(conn: &Connection, previous: &BlockProfile, updated: &BlockProfile) -> SqliteResult<bool> {
+    if previous.unlisted_mode != BlockMode::Soft && updated.unlisted_mode == BlockMode::Soft {
+        return Ok(true);
+    }
+    if previous.list_mode == updated.list_mode {
+        return Ok(false);
+    }
+    if previous.list_mode == ListMode::Allowlist {
+        return Ok(true);
+    }
+    let active = db::find_sessions_with_status(conn, &[SessionStatus::Running, SessionStatus::Paused])?;
+    Ok(active.iter().any(|session| session.profile_id.as_deref() == Some(previous.id.as_str())))
+}
+
+#[tauri::command]
+pub fn update_block_profile(
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    id: String,
+    updates: UpdateBlockProfile,
+    unlock_token: Option<String>,
+) -> CommandResult<BlockProfile> {
+    let list_mode: Option<ListMode> = updates.list_mode.as_deref().map(|v| db::parse_rule_field("listMode", v)).transpose()?;
+    let unlisted_mode = updates.unlisted_mode.as_deref().map(|v| db::parse_block_mode("unlistedMode", v)).transpose()?;
+
+    let conn = db.conn.lock()?;
+    let mut profile = find_profile(&conn, &id)?;
+    let previous = profile.clone();
+
+    if let Some(name) = updates.name {
+        profile.name = checked_name(&conn, &name, Some(&id))?;
+    }
+    if let Some(list_mode) = list_mode {
+        profile.list_mode = list_mode;
+    }
+    if let Some(unlisted_mode) = unlisted_mode {
+        profile.unlisted_mode = unlisted_mode;
+    }
+
+    if profile_loosened(&conn, &previous, &profile)? {
+        strict::ensure_unlocked(&conn, "loosen a block profile")?;
+        passphrase.ensure_authorized(&conn, unlock_token.as_deref(), "loosen a block profile")?;
+    }
+
+    conn.execute(
+        "UPDATE block_profiles SET name = ?1, list_mode = ?2, unlisted_mode = ?3 WHERE id = ?4",
+        (&profile.name, &profile.list_mode, &profile.unlisted_mode, &id),
+    )?;
+
+    Ok(profile)
+}
+
+// Deletes the profile and its rules. Sessions and schedules that used it fall
+// back to the default rule set.
+#[tauri::command]
+pub fn delete_block_profile(
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    id: String,
+    unlock_token: Option<String>,
+) -> CommandResult<()> {
+    let mut conn = db.conn.lock()?;
+    find_profile(&conn, &id)?;
+    strict::ensure_unlocked(&conn, "delete a block profile")?;
+    passphrase.ensure_authorized(&conn, unlock_token.as_deref(), "delete a block profile")?;
+
+    let in_use: bool = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) > 0 FROM sessions WHERE profile_id = ?1 AND status IN ('{}', '{}')",
+            SessionStatus::Running.as_str(),
+            SessionStatus::Paused.as_str()
+        ),
+        [&id],
+        |row| row.get(0),
+    )?;
+    if in_use {
+        return Err(FocusLockError::InvalidState(
+            "This profile is in use by the current session".to_string(),
+        ));
+    }
+
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM block_rules WHERE profile_id = ?1", [&id])?;
+    tx.execute("UPDATE sessions SET profile_id = NULL WHERE profile_id = ?1", [&id])?;
+    tx.execute("UPDATE session_schedules SET profile_id = NULL WHERE profile_id = ?1", [&id])?;
+    tx.execute("DELETE FROM block_profiles WHERE id = ?1", [&id])?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::find_block_rules;
+
+    #[test]
+    fn rules_are_scoped_to_their_profile() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+        conn.execute_batch(
+            "INSERT INTO block_profiles (id, name, created_utc) VALUES ('deep', 'Deep work', 0);
+             INSERT INTO block_rules VALUES ('r1', 'discord', 'exe', 'hard', NULL);
+             INSERT INTO block_rules VALUES ('r2', 'steam', 'exe', 'hard', 'deep');",
+        )
+        .unwrap();
+
+        let ids = |profile: Option<&str>| -> Vec<String> {
+            find_block_rules(&conn, profile).unwrap().into_iter().map(|r| r.id).collect()
+        };
+        assert_eq!(ids(None), vec!["r1"]);
+        assert_eq!(ids(Some("deep")), vec!["r2"]);
+        assert_eq!(find_profile(&conn, "deep").unwrap().rule_count, 1);
+
+        assert!(checked_name(&conn, " deep WORK ", None).is_err());
+        assert_eq!(checked_name(&conn, "Deep work", Some("deep")).unwrap(), "Deep work");
+        assert!(ensure_exists(&conn, Some("missing")).is_err());
+
+        assert!(matches!(policy_for(&conn, Some("deep")).unwrap(), Policy::Blocklist(_)));
+        conn.execute("UPDATE block_profiles SET list_mode = 'allowlist' WHERE id = 'deep'", []).unwrap();
+        assert!(matches!(policy_for(&conn, Some("deep")).unwrap(), Policy::Allowlist { mode: BlockMode::Hard, .. }));
+        assert!(matches!(policy_for(&conn, None).unwrap(), Policy::Blocklist(_)));
+    }
+
+    #[test]
+    fn any_list_mode_change_loosens_the_active_profile() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+        conn.execute("INSERT INTO block_profiles (id, name, created_utc) VALUES ('deep', 'Deep work', 0)", []).unwrap();
+
+        let blocklist = find_profile(&conn, "deep").unwrap();
+        let allowlist = BlockProfile { list_mode: ListMode::Allowlist, ..blocklist.clone() };
+        let soft = BlockProfile { unlisted_mode: BlockMode::Soft, ..blocklist.clone() };
+        assert!(!profile_loosened(&conn, &blocklist, &allowlist).unwrap());
+        assert!(profile_loosened(&conn, &allowlist, &blocklist).unwrap());
+        assert!(profile_loosened(&conn, &blocklist, &soft).unwrap());
+
+        conn.execute(
+            "INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs, profile_id)
+             VALUES ('s1', 0, 100, 'paused', 100, 'deep')",
+            [],
+        )
+        .unwrap();
+        assert!(profile_loosened(&conn, &blocklist, &allowlist).unwrap());
+        assert!(!profile_loosened(&conn, &blocklist, &blocklist).unwrap());
+    }
+}
@@ -0,0 +1,566 @@
+// Scheduled and recurring focus sessions
+//
+// A schedule is a weekly recurrence in local wall-clock time ("mon,tue,wed at
+// 09:00 for 3h"), so it follows the user across DST changes. A background
+// loop starts whichever occurrence is due, plus one-off sessions created with
+// status `scheduled`, and then hands off to the normal session engine and
+// enforcement monitor. Started and skipped occurrences are recorded in
+// `schedule_occurrences` so none fires twice.
+
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveTime, TimeZone, Weekday};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+use crate::db::{self, DbState, Session, SessionStatus};
+use crate::error::{CommandResult, FocusLockError, OrNotFound};
+use crate::session::{self, now_utc};
+
+pub const STARTED_EVENT: &str = "session-started";
+
+const SCHEDULER_INTERVAL_SECS: u64 = 5;
+const MAX_DURATION_SECS: i32 = 24 * 60 * 60;
+const DAY_NAMES: [(&str, Weekday); 7] = [
+    ("mon", Weekday::Mon),
+    ("tue", Weekday::Tue),
+    ("wed", Weekday::Wed),
+    ("thu", Weekday::Thu),
+    ("fri", Weekday::Fri),
+    ("sat", Weekday::Sat),
+    ("sun", Weekday::Sun),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub id: String,
+    pub name: String,
+    // Three-letter lowercase day names, e.g. ["mon", "wed"]
+    pub days: Vec<String>,
+    // Local time, HH:MM
+    pub start_time: String,
+    pub duration_secs: i32,
+    pub enabled: bool,
+    pub created_utc: i64,
+    // Block profile for the sessions it starts; None uses the default set
+    pub profile_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsertSchedule {
+    pub name: String,
+    pub days: Vec<String>,
+    pub start_time: String,
+    pub duration_secs: i32,
+    pub enabled: Option<bool>,
+    pub profile_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSchedule {
+    pub name: Option<String>,
+    pub days: Option<Vec<String>>,
+    pub start_time: Option<String>,
+    pub duration_secs: Option<i32>,
+    pub enabled: Option<bool>,
+    // Some(None) goes back to the default set
+    #[serde(default, deserialize_with = "db::double_option")]
+    pub profile_id: Option<Option<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Occurrence {
+    pub schedule_id: String,
+    pub schedule_name: String,
+    pub start_utc: i64,
+    pub end_utc: i64,
+    pub skipped: bool,
+    pub session_id: Option<String>,
+}
+
+// The parsed, validated form of a schedule's timing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recurrence {
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub duration_secs: i64,
+}
+
+impl Recurrence {
+    pub fn parse(days: &[String], start_time: &str, duration_secs: i32) -> CommandResult<Recurrence> {
+        if days.is_empty() {
+            return Err(FocusLockError::validation("days", "", "Pick at least one day"));
+        }
+
+        let mut weekdays = Vec::new();
+        for day in days {
+            let weekday = DAY_NAMES
+                .iter()
+                .find(|(name, _)| *name == day.as_str())
+                .map(|(_, weekday)| *weekday)
+                .ok_or_else(|| {
+                    FocusLockError::validation("days", day, format!("'{}' is not one of: mon, tue, wed, thu, fri, sat, sun", day))
+                })?;
+            if !weekdays.contains(&weekday) {
+                weekdays.push(weekday);
+            }
+        }
+
+        let start = NaiveTime::parse_from_str(start_time, "%H:%M")
+            .map_err(|_| FocusLockError::validation("startTime", start_time, "Start time must be HH:MM"))?;
+
+        if !(60..=MAX_DURATION_SECS).contains(&duration_secs) {
+            return Err(FocusLockError::validation(
+                "durationSecs",
+                &duration_secs.to_string(),
+                "Duration must be between one minute and 24 hours",
+            ));
+        }
+
+        Ok(Recurrence {
+            days: weekdays,
+            start,
+            duration_secs: duration_secs as i64,
+        })
+    }
+
+    fn start_on<Tz: TimeZone>(&self, date: NaiveDate, tz: &Tz) -> Option<i64> {
+        if !self.days.contains(&date.weekday()) {
+            return None;
+        }
+        // A start inside a DST gap doesn't exist that day; an ambiguous one
+        // (clocks going back) fires at the first of the two instants
+        tz.from_local_datetime(&date.and_time(self.start))
+            .earliest()
+            .map(|dt| dt.timestamp())
+    }
+
+    // Start times of every occurrence that overlaps [from, to)
+    pub fn occurrences<Tz: TimeZone>(&self, from: i64, to: i64, tz: &Tz) -> Vec<i64> {
+        let Some(first) = tz.timestamp_opt(from - self.duration_secs, 0).single() else {
+            return Vec::new();
+        };
+        let Some(last) = tz.timestamp_opt(to, 0).single() else {
+            return Vec::new();
+        };
+
+        let mut starts = Vec::new();
+        let mut date = first.date_naive() - ChronoDuration::days(1);
+        while date <= last.date_naive() + ChronoDuration::days(1) {
+            if let Some(start) = self.start_on(date, tz) {
+                if start < to && start + self.duration_secs > from {
+                    starts.push(start);
+                }
+            }
+            date += ChronoDuration::days(1);
+        }
+        starts
+    }
+}
+
+fn schedule_from_row(row: &rusqlite::Row) -> SqliteResult<Schedule> {
+    let days: String = row.get(2)?;
+    Ok(Schedule {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        days: days.split(',').filter(|d| !d.is_empty()).map(String::from).collect(),
+        start_time: row.get(3)?,
+        duration_secs: row.get(4)?,
+        enabled: row.get(5)?,
+        created_utc: row.get(6)?,
+        profile_id: row.get(7)?,
+    })
+}
+
+const SCHEDULE_COLUMNS: &str = "id, name, days, start_time, duration_secs, enabled, created_utc, profile_id";
+
+fn find_schedule(conn: &Connection, id: &str) -> CommandResult<Schedule> {
+    conn.query_row(
+        &format!("SELECT {} FROM session_schedules WHERE id = ?1", SCHEDULE_COLUMNS),
+        [id],
+        schedule_from_row,
+    )
+    .or_not_found("schedule", id)
+}
+
+fn all_schedules(conn: &Connection) -> SqliteResult<Vec<Schedule>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM session_schedules ORDER BY start_time, name",
+        SCHEDULE_COLUMNS
+    ))?;
+    let schedules = stmt
+        .query_map([], schedule_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    Ok(schedules)
+}
+
+fn recurrence(schedule: &Schedule) -> CommandResult<Recurrence> {
+    Recurrence::parse(&schedule.days, &schedule.start_time, schedule.duration_secs)
+}
+
+// (status, session_id) of an occurrence that was already started or skipped
+fn occurrence_record(conn: &Connection, schedule_id: &str, start_utc: i64) -> SqliteResult<Option<(String, Option<String>)>> {
+    conn.query_row(
+        "SELECT status, session_id FROM schedule_occurrences WHERE schedule_id = ?1 AND occurrence_utc = ?2",
+        (schedule_id, start_utc),
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+}
+
+// Tauri Commands - Schedules
+#[tauri::command]
+pub fn get_schedules(db: State<DbState>) -> CommandResult<Vec<Schedule>> {
+    let conn = db.conn.lock()?;
+    Ok(all_schedules(&conn)?)
+}
+
+#[tauri::command]
+pub fn create_schedule(db: State<DbState>, schedule: InsertSchedule) -> CommandResult<Schedule> {
+    if schedule.name.trim().is_empty() {
+        return Err(FocusLockError::validation("name", &schedule.name, "Name cannot be empty"));
+    }
+    Recurrence::parse(&schedule.days, &schedule.start_time, schedule.duration_secs)?;
+
+    let schedule = Schedule {
+        id: Uuid::new_v4().to_string(),
+        name: schedule.name,
+        days: schedule.days,
+        start_time: schedule.start_time,
+        duration_secs: schedule.duration_secs,
+        enabled: schedule.enabled.unwrap_or(true),
+        created_utc: now_utc(),
+        profile_id: schedule.profile_id,
+    };
+
+    let conn = db.conn.lock()?;
+    crate::profiles::ensure_exists(&conn, schedule.profile_id.as_deref())?;
+    conn.execute(
+        &format!("INSERT INTO session_schedules ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)", SCHEDULE_COLUMNS),
+        (
+            &schedule.id,
+            &schedule.name,
+            schedule.days.join(","),
+            &schedule.start_time,
+            schedule.duration_secs,
+            schedule.enabled,
+            schedule.created_utc,
+            &schedule.profile_id,
+        ),
+    )?;
+
+    Ok(schedule)
+}
+
+#[tauri::command]
+pub fn update_schedule(db: State<DbState>, id: String, updates: UpdateSchedule) -> CommandResult<Schedule> {
+    let conn = db.conn.lock()?;
+    let mut schedule = find_schedule(&conn, &id)?;
+
+    if let Some(name) = updates.name {
+        if name.trim().is_empty() {
+            return Err(FocusLockError::validation("name", &name, "Name cannot be empty"));
+        }
+        schedule.name = name;
+    }
+    if let Some(days) = updates.days {
+        schedule.days = days;
+    }
+    if let Some(start_time) = updates.start_time {
+        schedule.start_time = start_time;
+    }
+    if let Some(duration_secs) = updates.duration_secs {
+        schedule.duration_secs = duration_secs;
+    }
+    if let Some(enabled) = updates.enabled {
+        schedule.enabled = enabled;
+    }
+    if let Some(profile_id) = updates.profile_id {
+        crate::profiles::ensure_exists(&conn, profile_id.as_deref())?;
+        schedule.profile_id = profile_id;
+    }
+    recurrence(&schedule)?;
+
+    conn.execute(
+        "UPDATE session_schedules SET name = ?1, days = ?2, start_time = ?3, duration_secs = ?4, enabled = ?5, profile_id = ?6
+         WHERE id = ?7",
+        (
+            &schedule.name,
+            schedule.days.join(","),
+            &schedule.start_time,
+            schedule.duration_secs,
+            schedule.enabled,
+            &schedule.profile_id,
+            &id,
+        ),
+    )?;
+
+    Ok(schedule)
+}
+
+// Sessions already started from the schedule are left alone
+#[tauri::command]
+pub fn delete_schedule(db: State<DbState>, id: String) -> CommandResult<()> {
+    let conn = db.conn.lock()?;
+    if conn.execute("DELETE FROM session_schedules WHERE id = ?1", [&id])? == 0 {
+        return Err(FocusLockError::not_found("schedule", &id));
+    }
+    conn.execute("DELETE FROM schedule_occurrences WHERE schedule_id = ?1", [&id])?;
+    Ok(())
+}
+
+// Occurrences of enabled schedules from now (including any in progress) to
+// `days` days ahead, soonest first
+#[tauri::command]
+pub fn get_upcoming_occurrences(db: State<DbState>, days: Option<u32>) -> CommandResult<Vec<Occurrence>> {
+    let days = days.unwrap_or(7).clamp(1, 60) as i64;
+    let now = now_utc();
+
+    let conn = db.conn.lock()?;
+    let mut occurrences = Vec::new();
+
+    for schedule in all_schedules(&conn)?.into_iter().filter(|s| s.enabled) {
+        let Ok(recurrence) = recurrence(&schedule) else {
+            continue;
+        };
+
+        for start_utc in recurrence.occurrences(now, now + days * 24 * 60 * 60, &Local) {
+            let record = occurrence_record(&conn, &schedule.id, start_utc)?;
+            occurrences.push(Occurrence {
+                schedule_id: schedule.id.clone(),
+                schedule_name: schedule.name.clone(),
+                start_utc,
+                end_utc: start_utc + recurrence.duration_secs,
+                skipped: record.as_ref().is_some_and(|(status, _)| status == "skipped"),
+                session_id: record.and_then(|(_, session_id)| session_id),
+            });
+        }
+    }
+
+    occurrences.sort_by_key(|o| o.start_utc);
+    Ok(occurrences)
+}
+
+// Skips one occurrence without touching the rest of the schedule
+#[tauri::command]
+pub fn skip_occurrence(db: State<DbState>, schedule_id: String, start_utc: i64) -> CommandResult<Occurrence> {
+    let conn = db.conn.lock()?;
+    let schedule = find_schedule(&conn, &schedule_id)?;
+    let recurrence = recurrence(&schedule)?;
+    let now = now_utc();
+
+    let end_utc = start_utc + recurrence.duration_secs;
+    if !recurrence.occurrences(start_utc, start_utc + 1, &Local).contains(&start_utc) {
+        return Err(FocusLockError::validation(
+            "startUtc",
+            &start_utc.to_string(),
+            "Not an occurrence of this schedule",
+        ));
+    }
+    if end_utc <= now {
+        return Err(FocusLockError::InvalidState("That occurrence has already ended".to_string()));
+    }
+
+    match occurrence_record(&conn, &schedule_id, start_utc)? {
+        Some((status, _)) if status == "started" => {
+            return Err(FocusLockError::InvalidState(
+                "That occurrence has already started; end the session instead".to_string(),
+            ));
+        }
+        Some(_) => {}
+        None => {
+            conn.execute(
+                "INSERT INTO schedule_occurrences (schedule_id, occurrence_utc, status) VALUES (?1, ?2, 'skipped')",
+                (&schedule_id, start_utc),
+            )?;
+        }
+    }
+
+    Ok(Occurrence {
+        schedule_id,
+        schedule_name: schedule.name,
+        start_utc,
+        end_utc,
+        skipped: true,
+        session_id: None,
+    })
+}
+
+// Starts `session` (already in `scheduled` state) so that it ends at
+// `window_end`, however late the scheduler got to it
+fn start_in_window(conn: &Connection, session: &mut Session, window_end: i64, now: i64) -> CommandResult<()> {
+    session.remaining_secs = Some((window_end - now) as i32);
+    session::transition(session, SessionStatus::Running, now)?;
+    db::save_session(conn, session)?;
+    db::touch_session_heartbeat(conn, &session.id, now)?;
+    Ok(())
+}
+
+// One scheduler pass. Returns the session it started, if any. Only one
+// session runs at a time, so nothing starts while another is running or
+// paused; a due occurrence is picked up on a later pass if the other session
+// ends first.
+fn start_due_session(db: &DbState, now: i64) -> CommandResult<Option<Session>> {
+    let conn = db.conn.lock()?;
+
+    if !db::find_sessions_with_status(&conn, &[SessionStatus::Running, SessionStatus::Paused])?.is_empty() {
+        return Ok(None);
+    }
+
+    // One-off sessions created ahead of time
+    let mut scheduled = db::find_sessions_with_status(&conn, &[SessionStatus::Scheduled])?;
+    scheduled.sort_by_key(|s| s.start_utc);
+    for mut session in scheduled.into_iter().filter(|s| s.start_utc <= now) {
+        let window_end = session.start_utc + session.duration_secs as i64;
+        if window_end <= now {
+            // Its whole window passed while FocusLock wasn't running. Nobody
+            // gave up on it, so it isn't counted as canceled.
+            session::transition(&mut session, SessionStatus::Skipped, now)?;
+            db::save_session(&conn, &session)?;
+            println!("[Scheduler] Skipped session {} that was missed entirely", session.id);
+            continue;
+        }
+
+        start_in_window(&conn, &mut session, window_end, now)?;
+        println!("[Scheduler] Started scheduled session {}", session.id);
+        return Ok(Some(session));
+    }
+
+    // Recurring schedules
+    for schedule in all_schedules(&conn)?.into_iter().filter(|s| s.enabled) {
+        let Ok(recurrence) = recurrence(&schedule) else {
+            continue;
+        };
+
+        for start_utc in recurrence.occurrences(now, now + 1, &Local) {
+            if occurrence_record(&conn, &schedule.id, start_utc)?.is_some() {
+                continue;
+            }
+
+            let window_end = start_utc + recurrence.duration_secs;
+            let mut session = Session {
+                id: Uuid::new_v4().to_string(),
+                start_utc: now,
+                end_utc: window_end,
+                status: SessionStatus::Scheduled,
+                duration_secs: (window_end - now) as i32,
+                remaining_secs: None,
+                profile_id: schedule.profile_id.clone(),
+            };
+            db::insert_session(&conn, &session)?;
+            start_in_window(&conn, &mut session, window_end, now)?;
+
+            conn.execute(
+                "INSERT INTO schedule_occurrences (schedule_id, occurrence_utc, status, session_id) VALUES (?1, ?2, 'started', ?3)",
+                (&schedule.id, start_utc, &session.id),
+            )?;
+
+            println!("[Scheduler] Started session {} for schedule '{}'", session.id, schedule.name);
+            return Ok(Some(session));
+        }
+    }
+
+    Ok(None)
+}
+
+pub fn spawn_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let db: State<DbState> = app.state();
+            let now = now_utc();
+
+            match start_due_session(&db, now) {
+                Ok(Some(session)) => {
+                    crate::launch_session_monitor(&app);
+                    let _ = app.emit(
+                        session::TICK_EVENT,
+                        session::SessionTick {
+                            session_id: session.id.clone(),
+                            remaining_secs: session::remaining_secs(&session, now),
+                            end_utc: session.end_utc,
+                        },
+                    );
+                    let _ = app.emit(STARTED_EVENT, session);
+                }
+                Ok(None) => {}
+                Err(e) => println!("[Scheduler] Failed to start a due session: {}", e),
+            }
+
+            tokio::time::sleep(Duration::from_secs(SCHEDULER_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn weekdays_9_to_12() -> Recurrence {
+        let days: Vec<String> = ["mon", "tue", "wed", "thu", "fri"].iter().map(|d| d.to_string()).collect();
+        Recurrence::parse(&days, "09:00", 3 * 60 * 60).unwrap()
+    }
+
+    #[test]
+    fn weekly_occurrences_in_local_time() {
+        let tz = FixedOffset::east_opt(2 * 60 * 60).unwrap();
+        let recurrence = weekdays_9_to_12();
+
+        // Monday 2024-03-04 00:00 local to the following Monday
+        let monday = tz.with_ymd_and_hms(2024, 3, 4, 0, 0, 0).unwrap().timestamp();
+        let week = recurrence.occurrences(monday, monday + 7 * 24 * 60 * 60, &tz);
+
+        assert_eq!(week.len(), 5);
+        assert_eq!(week[0], tz.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap().timestamp());
+        assert_eq!(week[4], tz.with_ymd_and_hms(2024, 3, 8, 9, 0, 0).unwrap().timestamp());
+
+        // An occurrence already in progress still counts as due
+        let mid_morning = tz.with_ymd_and_hms(2024, 3, 5, 10, 30, 0).unwrap().timestamp();
+        assert_eq!(
+            recurrence.occurrences(mid_morning, mid_morning + 1, &tz),
+            vec![tz.with_ymd_and_hms(2024, 3, 5, 9, 0, 0).unwrap().timestamp()]
+        );
+
+        // Nothing on Saturday
+        let saturday = tz.with_ymd_and_hms(2024, 3, 9, 10, 0, 0).unwrap().timestamp();
+        assert!(recurrence.occurrences(saturday, saturday + 1, &tz).is_empty());
+    }
+
+    #[test]
+    fn recurrence_values_are_validated() {
+        let days = vec!["mon".to_string()];
+        assert!(Recurrence::parse(&days, "9am", 3600).is_err());
+        assert!(Recurrence::parse(&days, "25:00", 3600).is_err());
+        assert!(Recurrence::parse(&days, "09:00", 0).is_err());
+        assert!(Recurrence::parse(&["Monday".to_string()], "09:00", 3600).is_err());
+        assert!(Recurrence::parse(&[], "09:00", 3600).is_err());
+    }
+
+    #[test]
+    fn one_off_sessions_wait_for_a_paused_session_and_missed_ones_are_skipped() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+        conn.execute_batch(
+            "INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs, remaining_secs)
+             VALUES ('paused', 0, 600, 'paused', 600, 300);
+             INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs) VALUES ('missed', 100, 700, 'scheduled', 600);
+             INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs) VALUES ('due', 900, 1500, 'scheduled', 600);",
+        )
+        .unwrap();
+        let db = DbState { conn: std::sync::Mutex::new(conn) };
+
+        assert!(start_due_session(&db, 1000).unwrap().is_none());
+
+        db.conn.lock().unwrap().execute("UPDATE sessions SET status = 'completed' WHERE id = 'paused'", []).unwrap();
+        let started = start_due_session(&db, 1000).unwrap().unwrap();
+        assert_eq!((started.id.as_str(), started.status, started.end_utc), ("due", SessionStatus::Running, 1500));
+
+        let conn = db.conn.lock().unwrap();
+        assert_eq!(db::find_session(&conn, "missed").unwrap().status, SessionStatus::Skipped);
+    }
+}
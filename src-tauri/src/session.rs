@@ -0,0 +1,514 @@
+// Backend-owned session timer
+//
+// The session row is the source of truth: remaining time is always derived
+// from `end_utc` and the wall clock, so closing the window or suspending the
+// machine can't make the countdown drift. A one-second ticker completes
+// sessions that ran out and emits `session-tick` / `session-ended` events.
+
+use rusqlite::Connection;
+use serde::Serialize;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::db::{self, DbState, Session, SessionStatus};
+use crate::early_end;
+use crate::error::{CommandResult, FocusLockError, OrNotFound};
+use crate::freeze;
+use crate::passphrase::PassphraseState;
+use crate::pomodoro::{self, PomodoroStatus};
+use crate::strict;
+
+pub const TICK_EVENT: &str = "session-tick";
+pub const ENDED_EVENT: &str = "session-ended";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionTick {
+    pub session_id: String,
+    pub remaining_secs: i64,
+    pub end_utc: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionEnded {
+    pub session_id: String,
+    pub status: SessionStatus,
+}
+
+pub fn now_utc() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// scheduled → running → paused ⇄ running → completed/canceled
+pub fn check_transition(from: SessionStatus, to: SessionStatus) -> CommandResult<()> {
+    use SessionStatus::*;
+
+    let legal = match from {
+        Scheduled => matches!(to, Scheduled | Running | Canceled | Skipped),
+        Running => matches!(to, Running | Paused | Completed | Canceled),
+        Paused => matches!(to, Paused | Running | Completed | Canceled),
+        Completed | Canceled | Skipped => false,
+    };
+
+    if legal {
+        Ok(())
+    } else {
+        Err(FocusLockError::InvalidState(format!("Cannot move a {} session to {}", from, to)))
+    }
+}
+
+// Finishing an already finished session the same way again, e.g. the UI's
+// countdown reaching zero after the ticker completed the session. Callers
+// treat it as a no-op rather than an illegal transition.
+pub fn is_repeat_finish(from: SessionStatus, to: SessionStatus) -> bool {
+    from == to && matches!(from, SessionStatus::Completed | SessionStatus::Canceled)
+}
+
+pub fn remaining_secs(session: &Session, now: i64) -> i64 {
+    match session.status {
+        SessionStatus::Running => (session.end_utc - now).max(0),
+        SessionStatus::Scheduled | SessionStatus::Paused => {
+            session.remaining_secs.unwrap_or(session.duration_secs) as i64
+        }
+        SessionStatus::Completed | SessionStatus::Skipped => 0,
+        SessionStatus::Canceled => session.remaining_secs.unwrap_or(0) as i64,
+    }
+}
+
+// Moves a session to `to` at wall-clock time `now`, fixing up the timing
+// columns so remaining time can always be recomputed from the row alone
+pub fn transition(session: &mut Session, to: SessionStatus, now: i64) -> CommandResult<()> {
+    check_transition(session.status, to)?;
+
+    let from = session.status;
+    let remaining = remaining_secs(session, now);
+
+    match to {
+        SessionStatus::Scheduled | SessionStatus::Skipped => {}
+        SessionStatus::Running => {
+            if from == SessionStatus::Scheduled {
+                session.start_utc = now;
+            }
+            if from != SessionStatus::Running {
+                session.end_utc = now + remaining;
+                session.remaining_secs = None;
+            }
+        }
+        SessionStatus::Paused => {
+            session.remaining_secs = Some(remaining as i32);
+        }
+        SessionStatus::Completed => {
+            // Keep the scheduled end if the ticker got here late
+            session.end_utc = if from == SessionStatus::Running { session.end_utc.min(now) } else { now };
+            session.remaining_secs = Some(0);
+        }
+        SessionStatus::Canceled => {
+            session.end_utc = now;
+            session.remaining_secs = Some(remaining as i32);
+        }
+    }
+
+    session.status = to;
+    Ok(())
+}
+
+// Callers hold the connection lock, so a session can be created and started
+// without anything slipping in between. `unlock_token` is only checked when
+// the change stops enforcement early.
+pub fn apply(
+    conn: &Connection,
+    passphrase: &PassphraseState,
+    unlock_token: Option<&str>,
+    id: &str,
+    to: SessionStatus,
+) -> CommandResult<Session> {
+    apply_with_phase(conn, passphrase, unlock_token, id, to).map(|(session, _)| session)
+}
+
+// Same as `apply`, also returning the pomodoro phase that starting or
+// resuming opened so the caller can emit it
+pub fn apply_with_phase(
+    conn: &Connection,
+    passphrase: &PassphraseState,
+    unlock_token: Option<&str>,
+    id: &str,
+    to: SessionStatus,
+) -> CommandResult<(Session, Option<PomodoroStatus>)> {
+    let mut session = db::find_session(conn, id).or_not_found("session", id)?;
+    if is_repeat_finish(session.status, to) {
+        return Ok((session, None));
+    }
+
+    let now = now_utc();
+
+    // Pausing, canceling or completing before the end all stop enforcement early
+    let ends_early = match to {
+        SessionStatus::Paused | SessionStatus::Canceled => true,
+        SessionStatus::Completed => remaining_secs(&session, now) > 0,
+        SessionStatus::Scheduled | SessionStatus::Running | SessionStatus::Skipped => false,
+    };
+    if ends_early && session.status != to {
+        let action = format!("{} the current session", verb(to));
+        strict::ensure_unlocked(conn, &action)?;
+        passphrase.ensure_authorized(conn, unlock_token, &action)?;
+
+        // Pausing stops enforcement too, so with a cooldown both go through an
+        // early end request
+        if matches!(session.status, SessionStatus::Running | SessionStatus::Paused) {
+            early_end::ensure_no_cooldown(conn)?;
+        }
+    }
+
+    transition(&mut session, to, now)?;
+    db::save_session(conn, &session)?;
+
+    // Don't let a stale heartbeat from before a pause count as downtime
+    let mut phase = None;
+    if to == SessionStatus::Running {
+        db::touch_session_heartbeat(conn, &session.id, now)?;
+        phase = pomodoro::advance(conn, &session, now)?;
+    } else {
+        pomodoro::close_open_interval(conn, &session.id, now, !ends_early)?;
+    }
+
+    Ok((session, phase))
+}
+
+fn verb(to: SessionStatus) -> &'static str {
+    match to {
+        SessionStatus::Paused => "pause",
+        SessionStatus::Canceled => "cancel",
+        _ => "end",
+    }
+}
+
+pub fn emit_ended(app: &AppHandle, session: &Session) {
+    let _ = app.emit(
+        ENDED_EVENT,
+        SessionEnded {
+            session_id: session.id.clone(),
+            status: session.status,
+        },
+    );
+}
+
+// Ticker heartbeats older than this mean the app wasn't running
+const HEARTBEAT_GAP_SECS: i64 = 5;
+
+// Called once from `setup` before the UI loads. Any session still marked
+// running is either finished (its end passed while we were down) or resumed;
+// either way the time since the last heartbeat is recorded as an
+// interruption. Returns true if a session is still running and the monitor
+// should be restarted.
+pub fn recover_sessions(db: &DbState) -> CommandResult<bool> {
+    let conn = db.conn.lock()?;
+    let now = now_utc();
+    let mut still_running = false;
+
+    let sessions = db::find_sessions_with_status(&conn, &[SessionStatus::Running, SessionStatus::Paused])?;
+
+    for mut session in sessions {
+        // Paused sessions don't count down, so there is nothing to reconcile
+        if session.status == SessionStatus::Paused {
+            continue;
+        }
+
+        let last_seen = db::last_session_heartbeat(&conn, &session.id)?;
+        let gap_end = now.min(session.end_utc);
+
+        // The interval in progress when we went down ends there; the ticker
+        // opens one for whatever phase the session is in now
+        pomodoro::close_open_interval(&conn, &session.id, last_seen.unwrap_or(now).min(gap_end), false)?;
+
+        if let Some(last_seen) = last_seen {
+            if gap_end - last_seen > HEARTBEAT_GAP_SECS {
+                db::insert_session_interruption(&conn, &session.id, last_seen, gap_end, "app_not_running")?;
+                println!("[Session] Session {} was unenforced for {}s", session.id, gap_end - last_seen);
+            }
+        }
+
+        if session.end_utc <= now {
+            transition(&mut session, SessionStatus::Completed, now)?;
+            db::save_session(&conn, &session)?;
+            println!("[Session] Completed session {} that expired while FocusLock was down", session.id);
+        } else {
+            db::touch_session_heartbeat(&conn, &session.id, now)?;
+            println!("[Session] Resuming enforcement for session {}", session.id);
+            still_running = true;
+        }
+    }
+
+    // Apps frozen by sessions that ended (or went on a break) while we were down
+    let thawed = freeze::thaw_unenforced(&conn, now)?;
+    if thawed > 0 {
+        println!("[Session] Resumed {} process(es) left frozen by a session that isn't enforcing", thawed);
+    }
+
+    Ok(still_running)
+}
+
+// Tauri Commands - Session engine
+// Starts enforcement for a session that was just started and tells the UI
+pub fn emit_started(app: &AppHandle, session: &Session) {
+    crate::launch_session_monitor(app);
+    let _ = app.emit(
+        TICK_EVENT,
+        SessionTick {
+            session_id: session.id.clone(),
+            remaining_secs: remaining_secs(session, now_utc()),
+            end_utc: session.end_utc,
+        },
+    );
+}
+
+#[tauri::command]
+pub fn start_session(app: AppHandle, db: State<DbState>, passphrase: State<PassphraseState>, id: String) -> CommandResult<Session> {
+    let session = apply(&*db.conn.lock()?, &passphrase, None, &id, SessionStatus::Running)?;
+    emit_started(&app, &session);
+    Ok(session)
+}
+
+#[tauri::command]
+pub fn pause_session(
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    id: String,
+    unlock_token: Option<String>,
+) -> CommandResult<Session> {
+    apply(&*db.conn.lock()?, &passphrase, unlock_token.as_deref(), &id, SessionStatus::Paused)
+}
+
+#[tauri::command]
+pub fn resume_session(app: AppHandle, db: State<DbState>, passphrase: State<PassphraseState>, id: String) -> CommandResult<Session> {
+    let (session, phase) = apply_with_phase(&*db.conn.lock()?, &passphrase, None, &id, SessionStatus::Running)?;
+    crate::launch_session_monitor(&app);
+    if let Some(phase) = phase {
+        let _ = app.emit(pomodoro::PHASE_EVENT, phase);
+    }
+    Ok(session)
+}
+
+#[tauri::command]
+pub fn cancel_session(
+    app: AppHandle,
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    id: String,
+    unlock_token: Option<String>,
+) -> CommandResult<Session> {
+    let session = apply(&*db.conn.lock()?, &passphrase, unlock_token.as_deref(), &id, SessionStatus::Canceled)?;
+    emit_ended(&app, &session);
+    Ok(session)
+}
+
+#[tauri::command]
+pub fn complete_session(
+    app: AppHandle,
+    db: State<DbState>,
+    passphrase: State<PassphraseState>,
+    id: String,
+    unlock_token: Option<String>,
+) -> CommandResult<Session> {
+    let session = apply(&*db.conn.lock()?, &passphrase, unlock_token.as_deref(), &id, SessionStatus::Completed)?;
+    emit_ended(&app, &session);
+    Ok(session)
+}
+
+fn tick(app: &AppHandle) {
+    let db: State<DbState> = app.state();
+    let now = now_utc();
+
+    let mut ticks = Vec::new();
+    let mut phases: Vec<PomodoroStatus> = Vec::new();
+    let mut ended = Vec::new();
+    {
+        let Ok(conn) = db.conn.lock() else {
+            return;
+        };
+        let Ok(running) = db::find_sessions_with_status(&conn, &[SessionStatus::Running]) else {
+            return;
+        };
+
+        for mut session in running {
+            let _ = db::touch_session_heartbeat(&conn, &session.id, now);
+
+            if session.end_utc > now {
+                if let Ok(Some(phase)) = pomodoro::advance(&conn, &session, now) {
+                    phases.push(phase);
+                }
+                ticks.push(SessionTick {
+                    session_id: session.id.clone(),
+                    remaining_secs: session.end_utc - now,
+                    end_utc: session.end_utc,
+                });
+                continue;
+            }
+
+            if transition(&mut session, SessionStatus::Completed, now).is_ok()
+                && db::save_session(&conn, &session).is_ok()
+            {
+                let _ = pomodoro::close_open_interval(&conn, &session.id, session.end_utc, true);
+                ended.push(session);
+            }
+        }
+
+        // However the session ended or paused (here, a command, the
+        // scheduler, or a pomodoro break), anything it froze is resumed
+        // within a tick
+        let _ = freeze::thaw_unenforced(&conn, now);
+        let _ = early_end::close_finished(&conn, now);
+    } // Release lock before emitting
+
+    for t in ticks {
+        let _ = app.emit(TICK_EVENT, t);
+    }
+    for phase in phases {
+        let _ = app.emit(pomodoro::PHASE_EVENT, phase);
+    }
+    for session in ended {
+        println!("[Session] Completed session {}", session.id);
+        emit_ended(app, &session);
+    }
+}
+
+pub fn spawn_ticker(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tick(&app);
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use SessionStatus::*;
+
+    fn session(status: SessionStatus) -> Session {
+        Session {
+            id: "s1".to_string(),
+            start_utc: 1_000,
+            end_utc: 1_600,
+            status,
+            duration_secs: 600,
+            remaining_secs: None,
+            profile_id: None,
+        }
+    }
+
+    #[test]
+    fn transitions_follow_the_lifecycle() {
+        assert!(check_transition(Scheduled, Running).is_ok());
+        assert!(check_transition(Running, Paused).is_ok());
+        assert!(check_transition(Paused, Running).is_ok());
+        assert!(check_transition(Paused, Canceled).is_ok());
+        assert!(check_transition(Scheduled, Paused).is_err());
+        assert!(check_transition(Scheduled, Completed).is_err());
+        assert!(check_transition(Completed, Running).is_err());
+        assert!(check_transition(Canceled, Completed).is_err());
+        assert!(check_transition(Scheduled, Skipped).is_ok());
+        assert!(check_transition(Running, Skipped).is_err());
+        assert!(check_transition(Skipped, Running).is_err());
+
+        // Finishing twice the same way is tolerated, but not through a transition
+        assert!(is_repeat_finish(Completed, Completed));
+        assert!(is_repeat_finish(Canceled, Canceled));
+        assert!(!is_repeat_finish(Completed, Canceled));
+        assert!(!is_repeat_finish(Running, Running));
+    }
+
+    #[test]
+    fn remaining_time_comes_from_the_row() {
+        let mut s = session(Running);
+        assert_eq!(remaining_secs(&s, 1_100), 500);
+        assert_eq!(remaining_secs(&s, 2_000), 0);
+
+        s.status = Scheduled;
+        assert_eq!(remaining_secs(&s, 0), 600);
+        s.status = Paused;
+        s.remaining_secs = Some(240);
+        assert_eq!(remaining_secs(&s, 5_000), 240);
+        s.status = Completed;
+        assert_eq!(remaining_secs(&s, 0), 0);
+    }
+
+    #[test]
+    fn pausing_and_resuming_keeps_the_time_left() {
+        let mut s = session(Scheduled);
+        transition(&mut s, Running, 10_000).unwrap();
+        assert_eq!((s.start_utc, s.end_utc, s.remaining_secs), (10_000, 10_600, None));
+
+        transition(&mut s, Paused, 10_200).unwrap();
+        assert_eq!(s.remaining_secs, Some(400));
+
+        // Time spent paused doesn't count down
+        transition(&mut s, Running, 50_000).unwrap();
+        assert_eq!((s.start_utc, s.end_utc, s.remaining_secs), (10_000, 50_400, None));
+
+        transition(&mut s, Canceled, 50_100).unwrap();
+        assert_eq!((s.status, s.end_utc, s.remaining_secs), (Canceled, 50_100, Some(300)));
+        assert!(transition(&mut s, Running, 50_200).is_err());
+    }
+
+    #[test]
+    fn late_completion_keeps_the_scheduled_end() {
+        let mut s = session(Running);
+        transition(&mut s, Completed, 1_650).unwrap();
+        assert_eq!((s.end_utc, s.remaining_secs), (1_600, Some(0)));
+
+        let mut s = session(Paused);
+        s.remaining_secs = Some(100);
+        transition(&mut s, Completed, 1_300).unwrap();
+        assert_eq!(s.end_utc, 1_300);
+    }
+
+    #[test]
+    fn recovery_completes_expired_sessions_and_records_downtime() {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+        let now = now_utc();
+
+        let expired = Session { id: "expired".to_string(), start_utc: now - 700, end_utc: now - 100, ..session(Running) };
+        let live = Session { id: "live".to_string(), start_utc: now - 300, end_utc: now + 300, ..session(Running) };
+        let paused = Session {
+            id: "paused".to_string(),
+            remaining_secs: Some(120),
+            ..session(Paused)
+        };
+        for s in [&expired, &live, &paused] {
+            db::insert_session(&conn, s).unwrap();
+        }
+        db::touch_session_heartbeat(&conn, "expired", now - 400).unwrap();
+        db::touch_session_heartbeat(&conn, "live", now - 60).unwrap();
+
+        let db = DbState { conn: std::sync::Mutex::new(conn) };
+        assert!(recover_sessions(&db).unwrap());
+
+        let conn = db.conn.lock().unwrap();
+        let expired = db::find_session(&conn, "expired").unwrap();
+        assert_eq!((expired.status, expired.end_utc), (Completed, now - 100));
+        assert_eq!(db::find_session(&conn, "live").unwrap().status, Running);
+        let still_paused = db::find_session(&conn, "paused").unwrap();
+        assert_eq!((still_paused.status, still_paused.remaining_secs), (Paused, paused.remaining_secs));
+
+        // Downtime runs from the last heartbeat to now, or to the end if that came first
+        let gaps: Vec<(String, i64, i64)> = conn
+            .prepare("SELECT session_id, started_utc, ended_utc FROM session_interruptions ORDER BY session_id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            gaps,
+            vec![("expired".to_string(), now - 400, now - 100), ("live".to_string(), now - 60, now)]
+        );
+        assert_eq!(db::last_session_heartbeat(&conn, "live").unwrap(), Some(now));
+        assert_eq!(db::last_session_heartbeat(&conn, "paused").unwrap(), None);
+    }
+}
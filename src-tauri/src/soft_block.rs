@@ -0,0 +1,154 @@
+// Warnings for soft-blocked processes
+//
+// Soft rules leave the process running, so the warning is the whole point:
+// each hit raises a desktop notification and a `soft-block` event for the UI.
+// A process stays quiet for a cooldown after being warned about, set by the
+// `notificationCadence` setting; "none" keeps the event but drops the
+// desktop notification.
+
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::db;
+
+pub const SOFT_BLOCK_EVENT: &str = "soft-block";
+pub const NOTIFICATION_CADENCE_KEY: &str = "notificationCadence";
+
+// Used for the event when desktop notifications are off
+const QUIET_COOLDOWN_SECS: i64 = 5 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cadence {
+    Frequent,
+    Normal,
+    Minimal,
+    None,
+}
+
+impl Cadence {
+    // Unknown or missing values fall back to the frontend's default
+    pub fn from_setting(value: Option<&str>) -> Cadence {
+        match value {
+            Some("frequent") => Cadence::Frequent,
+            Some("minimal") => Cadence::Minimal,
+            Some("none") => Cadence::None,
+            _ => Cadence::Normal,
+        }
+    }
+
+    pub fn load(conn: &Connection) -> Cadence {
+        let value = db::get_setting(conn, NOTIFICATION_CADENCE_KEY).unwrap_or(None);
+        Cadence::from_setting(value.as_deref())
+    }
+
+    fn cooldown_secs(&self) -> i64 {
+        match self {
+            Cadence::Frequent => 60,
+            Cadence::Normal => 5 * 60,
+            Cadence::Minimal => 15 * 60,
+            Cadence::None => QUIET_COOLDOWN_SECS,
+        }
+    }
+
+    pub fn desktop_notifications(&self) -> bool {
+        *self != Cadence::None
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SoftBlock {
+    pub session_id: Option<String>,
+    pub rule_id: Option<String>,
+    pub rule_app_id: String,
+    pub process_name: String,
+    pub pid: u32,
+    pub timestamp_utc: i64,
+}
+
+// When each (pid, rule) was last warned about
+#[derive(Default)]
+pub struct SoftBlockTracker {
+    last_warned: HashMap<(u32, Option<String>), i64>,
+}
+
+impl SoftBlockTracker {
+    pub fn new() -> Self {
+        SoftBlockTracker::default()
+    }
+
+    // True if this hit should be reported, and records it as reported
+    pub fn should_warn(&mut self, pid: u32, rule_id: Option<&str>, now: i64, cadence: Cadence) -> bool {
+        let cooldown = cadence.cooldown_secs();
+
+        // Forget hits too old to matter so exited pids don't pile up
+        self.last_warned.retain(|_, at| now - *at < Cadence::Minimal.cooldown_secs());
+
+        let key = (pid, rule_id.map(String::from));
+        match self.last_warned.get(&key) {
+            Some(at) if now - at < cooldown => false,
+            _ => {
+                self.last_warned.insert(key, now);
+                true
+            }
+        }
+    }
+}
+
+pub fn warn(app: &AppHandle, cadence: Cadence, hit: SoftBlock) {
+    if cadence.desktop_notifications() {
+        let shown = app
+            .notification()
+            .builder()
+            .title("FocusLock")
+            .body(format!("{} is on your block list for this session", hit.process_name))
+            .show();
+        if let Err(e) = shown {
+            println!("[Monitor] Failed to show notification: {}", e);
+        }
+    }
+
+    let _ = app.emit(SOFT_BLOCK_EVENT, hit);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warnings_respect_the_cadence_cooldown() {
+        let mut tracker = SoftBlockTracker::new();
+
+        assert!(tracker.should_warn(1, Some("r1"), 0, Cadence::Normal));
+        assert!(!tracker.should_warn(1, Some("r1"), 60, Cadence::Normal));
+        // Another process or rule is a separate warning
+        assert!(tracker.should_warn(2, Some("r1"), 60, Cadence::Normal));
+        assert!(tracker.should_warn(1, None, 60, Cadence::Normal));
+        assert!(tracker.should_warn(1, Some("r1"), 300, Cadence::Normal));
+
+        assert!(tracker.should_warn(3, None, 0, Cadence::Frequent));
+        assert!(tracker.should_warn(3, None, 60, Cadence::Frequent));
+
+        assert_eq!(Cadence::from_setting(Some("none")), Cadence::None);
+        assert_eq!(Cadence::from_setting(Some("sometimes")), Cadence::Normal);
+        assert!(!Cadence::None.desktop_notifications());
+    }
+
+    #[test]
+    fn cadence_is_read_through_get_setting() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+        assert_eq!(Cadence::load(&conn), Cadence::Normal);
+
+        // The frontend stores settings as JSON strings
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, '\"minimal\"')",
+            [NOTIFICATION_CADENCE_KEY],
+        )
+        .unwrap();
+        assert_eq!(Cadence::load(&conn), Cadence::Minimal);
+    }
+}
@@ -0,0 +1,389 @@
+// Focus statistics for the dashboard
+//
+// Day and week boundaries are computed in the user's timezone (the system
+// local zone unless the caller passes a fixed offset), so a session at 00:30
+// counts towards the right day. A session is attributed to the day it
+// started on.
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, TimeZone};
+use rusqlite::{Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tauri::State;
+
+use crate::db::{DbState, Session, SessionStatus};
+use crate::enforcement::Action;
+use crate::error::{CommandResult, FocusLockError};
+use crate::session::now_utc;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsQuery {
+    // Range for the daily/weekly totals and most-blocked apps; streaks and
+    // completion rate always use the whole history
+    pub since_utc: Option<i64>,
+    pub until_utc: Option<i64>,
+    // Minutes east of UTC (UTC+2 → 120). Defaults to the system local zone.
+    pub utc_offset_minutes: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DayTotal {
+    pub date: String,
+    pub focused_minutes: i64,
+    pub sessions: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekTotal {
+    // Monday of the week, YYYY-MM-DD
+    pub week_start: String,
+    pub focused_minutes: i64,
+    pub sessions: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockedApp {
+    pub process_name: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusStats {
+    pub daily: Vec<DayTotal>,
+    pub weekly: Vec<WeekTotal>,
+    pub completed_sessions: i64,
+    pub canceled_sessions: i64,
+    pub completion_rate: Option<f64>,
+    pub current_streak_days: i64,
+    pub longest_streak_days: i64,
+    pub most_blocked_apps: Vec<BlockedApp>,
+}
+
+fn local_date<Tz: TimeZone>(tz: &Tz, ts: i64) -> Option<NaiveDate> {
+    tz.timestamp_opt(ts, 0).single().map(|dt: DateTime<Tz>| dt.date_naive())
+}
+
+// Seconds actually spent focusing: the elapsed part of the countdown, minus
+// any time FocusLock wasn't running to enforce it
+fn focused_secs(session: &Session, interrupted_secs: i64, now: i64) -> i64 {
+    let remaining = match session.status {
+        SessionStatus::Running => (session.end_utc - now).max(0),
+        _ => session.remaining_secs.unwrap_or(0) as i64,
+    };
+    let elapsed = match session.status {
+        SessionStatus::Scheduled | SessionStatus::Skipped => 0,
+        _ => (session.duration_secs as i64 - remaining).max(0),
+    };
+    (elapsed - interrupted_secs).max(0)
+}
+
+fn longest_run(days: &BTreeSet<NaiveDate>) -> i64 {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev: Option<NaiveDate> = None;
+
+    for day in days {
+        run = match prev {
+            Some(p) if *day - p == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        prev = Some(*day);
+    }
+
+    longest
+}
+
+// A streak is still "current" if the last completed day is today or
+// yesterday, so it doesn't reset at midnight before today's session
+fn current_run(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> i64 {
+    let mut day = if days.contains(&today) {
+        today
+    } else if days.contains(&(today - Duration::days(1))) {
+        today - Duration::days(1)
+    } else {
+        return 0;
+    };
+
+    let mut run = 0;
+    while days.contains(&day) {
+        run += 1;
+        day -= Duration::days(1);
+    }
+    run
+}
+
+pub fn compute_stats<Tz: TimeZone>(
+    sessions: &[Session],
+    interrupted_secs: &HashMap<String, i64>,
+    work_secs: &HashMap<String, i64>,
+    most_blocked_apps: Vec<BlockedApp>,
+    query: &StatsQuery,
+    tz: &Tz,
+    now: i64,
+) -> FocusStats {
+    let in_range = |ts: i64| {
+        query.since_utc.is_none_or(|since| ts >= since) && query.until_utc.is_none_or(|until| ts < until)
+    };
+
+    let mut daily: BTreeMap<NaiveDate, (i64, i64)> = BTreeMap::new();
+    let mut weekly: BTreeMap<NaiveDate, (i64, i64)> = BTreeMap::new();
+    let mut completed_days = BTreeSet::new();
+    let mut completed = 0;
+    let mut canceled = 0;
+
+    for session in sessions {
+        let Some(day) = local_date(tz, session.start_utc) else {
+            continue;
+        };
+
+        match session.status {
+            SessionStatus::Completed => {
+                completed += 1;
+                completed_days.insert(day);
+            }
+            SessionStatus::Canceled => canceled += 1,
+            _ => {}
+        }
+
+        if !in_range(session.start_utc) {
+            continue;
+        }
+
+        // Pomodoro sessions count their work intervals only, not the breaks
+        let secs = match work_secs.get(&session.id) {
+            Some(&work) => work,
+            None => focused_secs(session, interrupted_secs.get(&session.id).copied().unwrap_or(0), now),
+        };
+        if secs == 0 {
+            continue;
+        }
+
+        let week_start = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+        for (bucket, key) in [(&mut daily, day), (&mut weekly, week_start)] {
+            let entry = bucket.entry(key).or_default();
+            entry.0 += secs;
+            entry.1 += 1;
+        }
+    }
+
+    let today = local_date(tz, now).unwrap_or_default();
+
+    FocusStats {
+        daily: daily
+            .into_iter()
+            .map(|(date, (secs, sessions))| DayTotal {
+                date: date.to_string(),
+                focused_minutes: secs / 60,
+                sessions,
+            })
+            .collect(),
+        weekly: weekly
+            .into_iter()
+            .map(|(week_start, (secs, sessions))| WeekTotal {
+                week_start: week_start.to_string(),
+                focused_minutes: secs / 60,
+                sessions,
+            })
+            .collect(),
+        completed_sessions: completed,
+        canceled_sessions: canceled,
+        completion_rate: if completed + canceled > 0 {
+            Some(completed as f64 / (completed + canceled) as f64)
+        } else {
+            None
+        },
+        current_streak_days: current_run(&completed_days, today),
+        longest_streak_days: longest_run(&completed_days),
+        most_blocked_apps,
+    }
+}
+
+// Only processes that were actually stopped or warned about; failed
+// attempts and the domain names of website blocks are left out. Events are
+// stored aggregated, so the totals add up their counts.
+fn most_blocked_apps(conn: &Connection, query: &StatsQuery) -> SqliteResult<Vec<BlockedApp>> {
+    let actions = [Action::Killed, Action::Warned, Action::Frozen]
+        .iter()
+        .map(|a| format!("'{}'", a.as_str()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT process_name, SUM(count) AS total FROM block_events
+         WHERE (?1 IS NULL OR timestamp_utc >= ?1) AND (?2 IS NULL OR timestamp_utc < ?2) AND action IN ({})
+         GROUP BY process_name ORDER BY total DESC, process_name LIMIT 10",
+        actions
+    ))?;
+    let apps = stmt
+        .query_map((query.since_utc, query.until_utc), |row| {
+            Ok(BlockedApp {
+                process_name: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })?
+        .collect();
+    apps
+}
+
+#[tauri::command]
+pub fn get_focus_stats(db: State<DbState>, query: Option<StatsQuery>) -> CommandResult<FocusStats> {
+    let query = query.unwrap_or_default();
+    let conn = db.conn.lock()?;
+
+    let sessions = crate::db::find_sessions_with_status(&conn, &SessionStatus::ALL)?;
+
+    let mut stmt = conn
+        .prepare("SELECT session_id, SUM(ended_utc - started_utc) FROM session_interruptions GROUP BY session_id")?;
+    let interrupted_secs = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+
+    let most_blocked_apps = most_blocked_apps(&conn, &query)?;
+
+    let now = now_utc();
+    let work_secs = crate::pomodoro::work_secs_by_session(&conn, now)?;
+    let stats = match query.utc_offset_minutes {
+        Some(minutes) => {
+            let tz = FixedOffset::east_opt(minutes * 60).ok_or_else(|| {
+                FocusLockError::validation("utcOffsetMinutes", &minutes.to_string(), "UTC offset must be within ±24 hours")
+            })?;
+            compute_stats(&sessions, &interrupted_secs, &work_secs, most_blocked_apps, &query, &tz, now)
+        }
+        None => compute_stats(&sessions, &interrupted_secs, &work_secs, most_blocked_apps, &query, &Local, now),
+    };
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // UTC+2
+    fn tz() -> FixedOffset {
+        FixedOffset::east_opt(120 * 60).unwrap()
+    }
+
+    fn at(day: u32, hour: u32, min: u32) -> i64 {
+        tz().with_ymd_and_hms(2026, 3, day, hour, min, 0).unwrap().timestamp()
+    }
+
+    fn session(id: &str, start_utc: i64, duration_secs: i32, status: SessionStatus) -> Session {
+        Session {
+            id: id.to_string(),
+            start_utc,
+            end_utc: start_utc + duration_secs as i64,
+            status,
+            duration_secs,
+            remaining_secs: if status == SessionStatus::Completed { Some(0) } else { None },
+            profile_id: None,
+        }
+    }
+
+    #[test]
+    fn totals_are_bucketed_in_the_callers_timezone() {
+        let sessions = vec![
+            // Sunday 23:00 local, Sunday 21:00 UTC
+            session("sun", at(1, 23, 0), 3600, SessionStatus::Completed),
+            // Monday 00:30 local but still Sunday in UTC
+            session("mon", at(2, 0, 30), 3600, SessionStatus::Completed),
+            // Pomodoro session: only its work intervals count
+            session("pomo", at(2, 9, 0), 3600, SessionStatus::Completed),
+            session("later", at(3, 9, 0), 1800, SessionStatus::Scheduled),
+        ];
+        let interrupted = HashMap::from([("mon".to_string(), 600)]);
+        let work = HashMap::from([("pomo".to_string(), 1500)]);
+
+        let now = at(3, 12, 0);
+        let stats = compute_stats(&sessions, &interrupted, &work, Vec::new(), &StatsQuery::default(), &tz(), now);
+
+        assert_eq!(
+            stats.daily,
+            vec![
+                DayTotal { date: "2026-03-01".to_string(), focused_minutes: 60, sessions: 1 },
+                DayTotal { date: "2026-03-02".to_string(), focused_minutes: 50 + 25, sessions: 2 },
+            ]
+        );
+        assert_eq!(
+            stats.weekly,
+            vec![
+                WeekTotal { week_start: "2026-02-23".to_string(), focused_minutes: 60, sessions: 1 },
+                WeekTotal { week_start: "2026-03-02".to_string(), focused_minutes: 75, sessions: 2 },
+            ]
+        );
+        assert_eq!((stats.completed_sessions, stats.canceled_sessions), (3, 0));
+
+        // The range filters totals by start time
+        let query = StatsQuery { since_utc: Some(at(2, 0, 0)), ..Default::default() };
+        let stats = compute_stats(&sessions, &interrupted, &work, Vec::new(), &query, &tz(), now);
+        assert_eq!(stats.daily.len(), 1);
+    }
+
+    #[test]
+    fn streaks_count_consecutive_local_days() {
+        let sessions = vec![
+            session("a", at(1, 23, 50), 1800, SessionStatus::Completed),
+            session("b", at(2, 0, 10), 1800, SessionStatus::Completed),
+            session("c", at(3, 8, 0), 1800, SessionStatus::Completed),
+            session("d", at(5, 8, 0), 1800, SessionStatus::Completed),
+            session("e", at(6, 8, 0), 1800, SessionStatus::Canceled),
+        ];
+        let none = HashMap::new();
+        let stats = |now| compute_stats(&sessions, &none, &none, Vec::new(), &StatsQuery::default(), &tz(), now);
+
+        let today = stats(at(6, 10, 0));
+        assert_eq!((today.longest_streak_days, today.current_streak_days), (3, 1));
+        assert_eq!(today.completion_rate, Some(0.8));
+
+        // Yesterday's session keeps the streak alive until today is over
+        assert_eq!(stats(at(4, 23, 0)).current_streak_days, 3);
+        assert_eq!(stats(at(7, 0, 30)).current_streak_days, 0);
+    }
+
+    #[test]
+    fn most_blocked_apps_add_up_aggregated_counts() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+        let db = DbState { conn: std::sync::Mutex::new(conn) };
+
+        let event = |process_name: &str, action: &str, count: i64| crate::db::NewBlockEvent {
+            timestamp_utc: 1000,
+            session_id: None,
+            rule_id: None,
+            process_name: process_name.to_string(),
+            exe_path: None,
+            pid: None,
+            action: action.to_string(),
+            count,
+        };
+        crate::db::record_block_events(
+            &db,
+            &[
+                event("steam", Action::Killed.as_str(), 1),
+                event("steam", Action::Killed.as_str(), 1),
+                event("discord", Action::Warned.as_str(), 5),
+                event("reddit.com", crate::dns::DNS_BLOCKED_ACTION, 40),
+                event("news.example", "url_blocked", 9),
+            ],
+        )
+        .unwrap();
+
+        let conn = db.conn.lock().unwrap();
+        let apps = most_blocked_apps(&conn, &StatsQuery::default()).unwrap();
+        assert_eq!(
+            apps,
+            vec![
+                BlockedApp { process_name: "discord".to_string(), count: 5 },
+                BlockedApp { process_name: "steam".to_string(), count: 2 },
+            ]
+        );
+
+        let query = StatsQuery { since_utc: Some(2000), ..Default::default() };
+        assert!(most_blocked_apps(&conn, &query).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+// Strict mode: while a session is active, refuse anything that would weaken
+// protection (removing or loosening rules, ending the session early, stopping
+// the monitor, turning strict mode off). Adding or tightening rules is always
+// allowed.
+
+use rusqlite::{Connection, Result as SqliteResult};
+
+use crate::db::{self, SessionStatus};
+use crate::error::{CommandResult, FocusLockError};
+
+pub const STRICT_MODE_KEY: &str = "strictMode";
+
+pub fn strict_mode_enabled(conn: &Connection) -> SqliteResult<bool> {
+    Ok(db::get_setting(conn, STRICT_MODE_KEY)?.as_deref() == Some("true"))
+}
+
+// Paused sessions count too, otherwise pausing would be a way around the lock
+pub fn session_active(conn: &Connection) -> SqliteResult<bool> {
+    let active = db::find_sessions_with_status(conn, &[SessionStatus::Running, SessionStatus::Paused])?;
+    Ok(!active.is_empty())
+}
+
+pub fn is_locked(conn: &Connection) -> SqliteResult<bool> {
+    Ok(strict_mode_enabled(conn)? && session_active(conn)?)
+}
+
+// Fails with `StrictModeLocked` if `action` has to wait for the session to end
+pub fn ensure_unlocked(conn: &Connection, action: &str) -> CommandResult<()> {
+    if is_locked(conn)? {
+        return Err(FocusLockError::StrictModeLocked {
+            action: action.to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locked_only_with_strict_mode_and_an_active_session() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn, None).unwrap();
+        conn.execute(
+            "INSERT INTO sessions (id, start_utc, end_utc, status, duration_secs) VALUES ('s1', 0, 100, 'paused', 100)",
+            [],
+        )
+        .unwrap();
+        assert!(!is_locked(&conn).unwrap());
+
+        conn.execute("INSERT INTO settings (key, value) VALUES (?1, 'true')", [STRICT_MODE_KEY]).unwrap();
+        assert!(is_locked(&conn).unwrap());
+        let err = ensure_unlocked(&conn, "delete a block rule").unwrap_err();
+        assert_eq!(err.code(), "strictModeLocked");
+        assert_eq!(err.to_string(), "Strict mode is on: can't delete a block rule until the current session ends");
+
+        conn.execute("UPDATE sessions SET status = 'canceled'", []).unwrap();
+        assert!(ensure_unlocked(&conn, "delete a block rule").is_ok());
+    }
+}